@@ -0,0 +1,141 @@
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Result};
+
+/// Derives `ccapi::structs::ConsoleStruct` for a struct with named fields
+///
+/// Fields are decoded and encoded as big-endian values. By default each field
+/// directly follows the previous one, which can be changed per field:
+///
+/// * `#[offset(0x..)]` - Places the field at the given offset from the start of the struct
+///
+/// The total size is the end of the furthest field, unless set with `#[size(0x..)]`
+/// on the struct itself. Decoding and encoding return an error if a field does not
+/// fit within that size.
+#[proc_macro_derive(ConsoleStruct, attributes(offset, size))]
+pub fn derive_console_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    name,
+                    "ConsoleStruct requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "ConsoleStruct can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut offsets = Vec::new();
+    let mut next_offset = quote!(0usize);
+
+    for field in fields {
+        let ty = &field.ty;
+        let offset = match attribute_expr(&field.attrs, "offset")? {
+            Some(expr) => quote!((#expr) as usize),
+            None => next_offset.clone(),
+        };
+
+        next_offset = quote!(#offset + <#ty as ::ccapi::structs::ConsoleStruct>::SIZE);
+        offsets.push(offset);
+    }
+
+    let size = match attribute_expr(&input.attrs, "size")? {
+        Some(expr) => quote!((#expr) as usize),
+        None => {
+            let ends = fields.iter().zip(&offsets).map(|(field, offset)| {
+                let ty = &field.ty;
+                quote!(#offset + <#ty as ::ccapi::structs::ConsoleStruct>::SIZE)
+            });
+
+            quote!({
+                let mut size = 0usize;
+                #(size = ::ccapi::structs::max_size(size, #ends);)*
+                size
+            })
+        }
+    };
+
+    let decoded_fields = fields.iter().zip(&offsets).map(|(field, offset)| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        quote! {
+            #ident: {
+                let range = ::ccapi::structs::field_range::<Self>(
+                    stringify!(#ident),
+                    #offset,
+                    <#ty as ::ccapi::structs::ConsoleStruct>::SIZE,
+                )?;
+                <#ty as ::ccapi::structs::ConsoleStruct>::decode(&bytes[range])?
+            }
+        }
+    });
+
+    let encoded_fields = fields.iter().zip(&offsets).map(|(field, offset)| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        quote! {
+            {
+                let range = ::ccapi::structs::field_range::<Self>(
+                    stringify!(#ident),
+                    #offset,
+                    <#ty as ::ccapi::structs::ConsoleStruct>::SIZE,
+                )?;
+                <#ty as ::ccapi::structs::ConsoleStruct>::encode(&self.#ident, &mut bytes[range])?;
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::ccapi::structs::ConsoleStruct for #name #type_generics #where_clause {
+            const SIZE: usize = #size;
+
+            fn decode(bytes: &[u8]) -> ::core::result::Result<Self, ::ccapi::structs::Error> {
+                ::ccapi::structs::check_size::<Self>(bytes.len())?;
+
+                Ok(#name {
+                    #(#decoded_fields,)*
+                })
+            }
+
+            fn encode(&self, bytes: &mut [u8]) -> ::core::result::Result<(), ::ccapi::structs::Error> {
+                ::ccapi::structs::check_size::<Self>(bytes.len())?;
+
+                #(#encoded_fields)*
+
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Returns the expression given to an attribute such as `#[offset(0x10)]`
+fn attribute_expr(attrs: &[syn::Attribute], name: &str) -> Result<Option<Expr>> {
+    match attrs.iter().find(|attr| attr.path().is_ident(name)) {
+        Some(attr) => Ok(Some(attr.parse_args::<Expr>()?)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,258 @@
+//! Parsing and applying Artemis PS3 cheat codes
+//!
+//! Artemis PS3 (by Dnawrkshp) shares its code types with NetCheat PS3 by the same
+//! author; see the type table of the [netcheat](crate::netcheat) module. Only the file
+//! layout differs: each code starts with a `#<name>` header line, followed by one line
+//! per instruction in the `<type> <address> <value>` form. Empty lines and lines
+//! starting with `//` are ignored.
+//!
+//! ```text
+//! #Infinite Health
+//! 0 00A1B2C4 38600001
+//! #Unlimited Ammo
+//! 6 00C0FFEE 00000010
+//! 0 00000004 000003E7
+//! ```
+//!
+//! Type `0` writes every byte of its value, so `38600001` is written as four bytes.
+
+use crate::netcheat::{self, parse_error, CodeLine};
+use crate::worker::Worker;
+use crate::{CodeParseError, CCAPI};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CODE_HEADER_PREFIX: char = '#';
+const COMMENT_PREFIX: &str = "//";
+
+/// A named Artemis code
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtemisCode {
+    pub name: String,
+    pub lines: Vec<CodeLine>,
+}
+
+impl ArtemisCode {
+    /// Runs every line of the code once against a process
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to run the code on
+    /// * `pid` - The process identifier to run the code against
+    pub fn apply(&self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        netcheat::run_lines(ccapi, pid, &self.name, &self.lines)
+    }
+}
+
+/// Parses Artemis codes, reporting the line number of the first invalid line
+pub fn parse(source: &str) -> Result<Vec<ArtemisCode>, CodeParseError> {
+    let mut blocks: Vec<(String, Vec<(usize, &str)>)> = Vec::new();
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let number = index + 1;
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+            continue;
+        }
+
+        if line.starts_with(CODE_HEADER_PREFIX) {
+            let name = line[CODE_HEADER_PREFIX.len_utf8()..].trim().to_string();
+            blocks.push((name, Vec::new()));
+            continue;
+        }
+
+        match blocks.last_mut() {
+            Some((_, lines)) => lines.push((number, line)),
+            None => {
+                return Err(parse_error(
+                    number,
+                    "code line found before any '#<name>' header",
+                ))
+            }
+        }
+    }
+
+    blocks
+        .into_iter()
+        .map(|(name, lines)| {
+            Ok(ArtemisCode {
+                name,
+                lines: netcheat::parse_lines(&lines)?,
+            })
+        })
+        .collect()
+}
+
+/// Loads and parses an Artemis code file
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<ArtemisCode>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+
+    parse(&source).with_context(|| format!("invalid Artemis code file {path:?}"))
+}
+
+/// Applies a set of Artemis codes to a process, once or continuously
+///
+/// Codes are enabled by default and can be toggled by name while running continuously.
+pub struct ArtemisEngine {
+    codes: Vec<ArtemisCode>,
+    disabled: Arc<Mutex<HashSet<String>>>,
+    worker: Option<Worker>,
+}
+
+impl ArtemisEngine {
+    pub fn new(codes: Vec<ArtemisCode>) -> Self {
+        ArtemisEngine {
+            codes,
+            disabled: Arc::default(),
+            worker: None,
+        }
+    }
+
+    /// Returns all loaded codes
+    pub fn codes(&self) -> &[ArtemisCode] {
+        &self.codes
+    }
+
+    /// Enables or disables a code by name
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        if !self.codes.iter().any(|code| code.name == name) {
+            bail!("No Artemis code named '{name}' is loaded");
+        }
+
+        let mut disabled = self.disabled.lock().unwrap();
+
+        match enabled {
+            true => disabled.remove(name),
+            false => disabled.insert(name.to_string()),
+        };
+
+        Ok(())
+    }
+
+    /// Returns whether a code is enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.lock().unwrap().contains(name)
+    }
+
+    /// Runs every enabled code once against a process
+    pub fn apply_once(&self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        apply_enabled(&self.codes, &self.disabled, ccapi, pid)
+    }
+
+    /// Keeps running every enabled code on a background thread until stopped
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to run the codes on
+    /// * `pid` - The process identifier to run the codes against
+    /// * `interval` - How long to wait between each run of the codes
+    pub fn start(&mut self, ccapi: &CCAPI, pid: u32, interval: Duration) -> Result<()> {
+        if self.is_running() {
+            bail!("Artemis codes are already being applied");
+        }
+
+        let codes = self.codes.clone();
+        let disabled = Arc::clone(&self.disabled);
+        let ccapi = ccapi.clone();
+
+        self.worker = Some(Worker::spawn(move |signal| loop {
+            apply_enabled(&codes, &disabled, &ccapi, pid)?;
+
+            if !signal.sleep(interval) {
+                return Ok(());
+            }
+        }));
+
+        Ok(())
+    }
+
+    /// Returns whether codes are being applied continuously
+    pub fn is_running(&self) -> bool {
+        self.worker.as_ref().map_or(false, Worker::is_running)
+    }
+
+    /// Stops applying codes continuously, returning the error that ended it early (if any)
+    pub fn stop(&mut self) -> Result<()> {
+        match self.worker.take() {
+            Some(mut worker) => worker.stop(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn apply_enabled(
+    codes: &[ArtemisCode],
+    disabled: &Mutex<HashSet<String>>,
+    ccapi: &CCAPI,
+    pid: u32,
+) -> Result<()> {
+    for code in codes {
+        if !disabled.lock().unwrap().contains(&code.name) {
+            code.apply(ccapi, pid)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netcheat::Operation;
+
+    #[test]
+    fn writes_every_byte_of_type_zero_values() {
+        let codes = parse("#Code\n0 00010000 38600001\n").unwrap();
+
+        assert_eq!(
+            codes[0].lines,
+            vec![CodeLine {
+                line: 2,
+                operation: Operation::Write {
+                    address: 0x10000,
+                    bytes: vec![0x38, 0x60, 0x00, 0x01],
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_headers_comments_and_two_line_codes() {
+        let codes = parse(
+            "// Header comment\n#First\n\n6 00010000 10\n0 00000004 01\n#Second\nB 00010000 00020000\nB 0A0B 0C0D\n",
+        )
+        .unwrap();
+
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0].name, "First");
+        assert_eq!(codes[0].lines[0].line, 4);
+        assert_eq!(
+            codes[1].lines,
+            vec![CodeLine {
+                line: 7,
+                operation: Operation::FindReplace {
+                    start: 0x10000,
+                    end: 0x20000,
+                    find: vec![0x0A, 0x0B],
+                    replace: vec![0x0C, 0x0D],
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        assert_eq!(parse("0 00010000 01\n").unwrap_err().line, 1);
+        assert_eq!(
+            parse("#A\n// comment\n0 00010000 XY\n").unwrap_err().line,
+            3
+        );
+        assert_eq!(parse("#A\nB 00010000 00020000\n#B\n").unwrap_err().line, 2);
+    }
+}
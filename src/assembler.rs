@@ -0,0 +1,709 @@
+//! Assembling PowerPC (Cell PPU) instructions into big-endian patch bytes
+//!
+//! ```
+//! use ccapi::assembler;
+//!
+//! let source = "
+//!     li r3, 10
+//! loop:
+//!     addi r3, r3, -1
+//!     cmpwi r3, 0
+//!     bne loop
+//!     blr
+//! ";
+//!
+//! let bytes = assembler::assemble(source, 0x10000).unwrap();
+//! assert_eq!(&bytes[..4], &[0x38, 0x60, 0x00, 0x0A]);
+//! assert_eq!(&bytes[12..16], &[0x40, 0x82, 0xFF, 0xF8]);
+//! ```
+//!
+//! Supported instructions:
+//!
+//! * Immediates: `li`, `lis`, `addi`, `addis`, `ori`, `oris`
+//! * Registers: `mr`, `add`, `subf`, `mflr`, `mtlr`, `mtctr`
+//! * Loads and stores: `lbz`, `lhz`, `lwz`, `lwzu`, `ld`, `stb`, `sth`, `stw`, `stwu`, `std`,
+//!   `lfs`, `lfd`, `stfs`, `stfd`
+//! * Comparisons: `cmpwi`, `cmplwi`, `cmpw`, `cmplw`
+//! * Branches: `b`, `bl`, `ba`, `bla`, `bc`, `bcl`, `beq`, `bne`, `blt`, `bge`, `bgt`, `ble`,
+//!   `bdnz`, `blr`, `blrl`, `bctr`, `bctrl`
+//! * Others: `nop`, `.long <value>`
+//!
+//! Branch targets are labels (`name:`) or absolute addresses. Immediates accept the
+//! `@h`, `@ha` and `@l` suffixes for splitting 32-bit values.
+
+use crate::CodeParseError;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Encoding of `nop` (`ori r0, r0, 0`)
+pub const NOP: u32 = 0x6000_0000;
+
+/// Encoding of `blr`
+pub const BLR: u32 = 0x4E80_0020;
+
+/// Size in bytes of every instruction
+pub const INSTRUCTION_SIZE: u64 = 4;
+const COMMENT_PREFIXES: &[char] = &['#', ';'];
+
+/// Label names and their addresses
+type Labels = HashMap<String, u64>;
+
+/// Line number and text of an instruction, without comments and labels
+type Line<'a> = (usize, &'a str);
+
+/// Assembles source text into big-endian bytes, as loaded at the given address
+///
+/// ### Arguments
+///
+/// * `source` - Instructions and labels, one per line
+/// * `address` - The address the first instruction will be written to
+pub fn assemble(source: &str, address: u64) -> Result<Vec<u8>, CodeParseError> {
+    let words = assemble_words(source, address)?;
+    Ok(words
+        .iter()
+        .flat_map(|word| word.to_be_bytes().to_vec())
+        .collect())
+}
+
+/// Assembles source text into instruction words, as loaded at the given address
+pub fn assemble_words(source: &str, address: u64) -> Result<Vec<u32>, CodeParseError> {
+    let (labels, lines) = split_lines(source, address)?;
+
+    // Second pass: encode with all labels known
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, (number, line))| {
+            let context = Context {
+                address: line_address(address, index, *number)?,
+                labels: &labels,
+                line: *number,
+            };
+
+            encode(&context, line)
+        })
+        .collect()
+}
+
+/// Returns the amount of bytes source text assembles to, which is the same at every address
+pub fn assembled_size(source: &str) -> Result<u64, CodeParseError> {
+    let (_, lines) = split_lines(source, 0)?;
+    Ok(lines.len() as u64 * INSTRUCTION_SIZE)
+}
+
+/// First pass: strips comments and labels, assigning addresses to the labels
+fn split_lines(source: &str, address: u64) -> Result<(Labels, Vec<Line<'_>>), CodeParseError> {
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let number = index + 1;
+        let mut line = raw_line.split(COMMENT_PREFIXES).next().unwrap_or("").trim();
+
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+
+            if !is_identifier(label) {
+                return Err(error(number, &format!("invalid label '{label}'")));
+            }
+
+            let label_address = line_address(address, lines.len(), number)?;
+
+            if labels.insert(label.to_string(), label_address).is_some() {
+                return Err(error(number, &format!("label '{label}' is defined twice")));
+            }
+
+            line = line[colon + 1..].trim();
+        }
+
+        if !line.is_empty() {
+            lines.push((number, line));
+        }
+    }
+
+    Ok((labels, lines))
+}
+
+/// Returns the address of the instruction at the given index
+fn line_address(address: u64, index: usize, line: usize) -> Result<u64, CodeParseError> {
+    (index as u64)
+        .checked_mul(INSTRUCTION_SIZE)
+        .and_then(|offset| address.checked_add(offset))
+        .ok_or_else(|| error(line, "instruction address overflows"))
+}
+
+/// Encodes a relative `b` (or `bl`) instruction branching from one address to another
+pub fn encode_branch(from: u64, to: u64, link: bool) -> Result<u32, CodeParseError> {
+    let offset = to.wrapping_sub(from) as i64;
+    branch_word(0, offset, false, link)
+}
+
+fn branch_word(
+    line: usize,
+    offset: i64,
+    absolute: bool,
+    link: bool,
+) -> Result<u32, CodeParseError> {
+    if offset % 4 != 0 {
+        return Err(error(line, "branch target is not aligned to 4 bytes"));
+    }
+
+    if !(-0x0200_0000..0x0200_0000).contains(&offset) {
+        return Err(error(
+            line,
+            &format!("branch target is out of range ({offset:#x})"),
+        ));
+    }
+
+    Ok(18 << 26 | (offset as u32 & 0x03FF_FFFC) | (absolute as u32) << 1 | link as u32)
+}
+
+struct Context<'a> {
+    address: u64,
+    labels: &'a Labels,
+    line: usize,
+}
+
+impl Context<'_> {
+    fn error(&self, message: &str) -> CodeParseError {
+        error(self.line, message)
+    }
+
+    fn register(&self, raw: &str) -> Result<u32, CodeParseError> {
+        let raw = raw.trim().trim_start_matches('%');
+
+        let number = match raw {
+            "sp" => Some(1),
+            "rtoc" | "toc" => Some(2),
+            _ if raw.starts_with('r') => raw[1..].parse::<u32>().ok(),
+            _ => raw.parse::<u32>().ok(),
+        };
+
+        match number {
+            Some(number) if number < 32 => Ok(number),
+            _ => Err(self.error(&format!("invalid register '{raw}'"))),
+        }
+    }
+
+    fn float_register(&self, raw: &str) -> Result<u32, CodeParseError> {
+        let raw = raw.trim().trim_start_matches('%');
+
+        match raw.starts_with('f') {
+            true => match raw[1..].parse::<u32>().ok() {
+                Some(number) if number < 32 => Ok(number),
+                _ => Err(self.error(&format!("invalid floating point register '{raw}'"))),
+            },
+            false => Err(self.error(&format!("invalid floating point register '{raw}'"))),
+        }
+    }
+
+    fn cr_field(&self, raw: &str) -> Result<u32, CodeParseError> {
+        let raw = raw.trim();
+
+        let field = match raw.starts_with("cr") {
+            true => &raw[2..],
+            false => raw,
+        };
+
+        match field.parse::<u32>().ok() {
+            Some(field) if field < 8 => Ok(field),
+            _ => Err(self.error(&format!("invalid condition register field '{raw}'"))),
+        }
+    }
+
+    /// Parses a number or label, applying an optional `@h`, `@ha` or `@l` suffix
+    fn value(&self, raw: &str) -> Result<i64, CodeParseError> {
+        let raw = raw.trim();
+
+        let (raw_value, suffix) = match raw.find('@') {
+            Some(at) => (&raw[..at], Some(&raw[at + 1..])),
+            None => (raw, None),
+        };
+
+        let value = match (parse_number(raw_value), self.labels.get(raw_value)) {
+            (Some(value), _) => value,
+            (None, Some(address)) => i64::try_from(*address).map_err(|_| {
+                self.error(&format!("address of label '{raw_value}' is out of range"))
+            })?,
+            (None, None) if is_number(raw_value) => {
+                return Err(self.error(&format!("number '{raw_value}' is out of range")))
+            }
+            (None, None) => {
+                return Err(self.error(&format!("unknown value or label '{raw_value}'")))
+            }
+        };
+
+        match suffix {
+            None => Ok(value),
+            Some("l") => Ok(value & 0xFFFF),
+            Some("h") => Ok((value >> 16) & 0xFFFF),
+            // Rounded up when the low half is negative, without overflowing like `value + 0x8000`
+            Some("ha") => Ok(((value >> 16) + ((value >> 15) & 1)) & 0xFFFF),
+            Some(other) => Err(self.error(&format!("unknown suffix '@{other}'"))),
+        }
+    }
+
+    fn signed_immediate(&self, raw: &str) -> Result<u32, CodeParseError> {
+        let value = self.value(raw)?;
+
+        // Halves produced by `@h`, `@ha` and `@l` are accepted as raw bits
+        match value {
+            -0x8000..=0x7FFF => Ok(value as u32 & 0xFFFF),
+            0x8000..=0xFFFF if raw.contains('@') => Ok(value as u32),
+            _ => Err(self.error(&format!("signed immediate '{raw}' is out of range"))),
+        }
+    }
+
+    fn high_immediate(&self, raw: &str) -> Result<u32, CodeParseError> {
+        match self.value(raw)? {
+            value @ -0x8000..=0xFFFF => Ok(value as u32 & 0xFFFF),
+            _ => Err(self.error(&format!("immediate '{raw}' is out of range"))),
+        }
+    }
+
+    fn unsigned_immediate(&self, raw: &str) -> Result<u32, CodeParseError> {
+        match self.value(raw)? {
+            value @ 0..=0xFFFF => Ok(value as u32),
+            _ => Err(self.error(&format!("unsigned immediate '{raw}' is out of range"))),
+        }
+    }
+
+    /// Parses a `d(rA)` memory operand
+    fn memory(&self, raw: &str) -> Result<(u32, u32), CodeParseError> {
+        let raw = raw.trim();
+
+        match (raw.find('('), raw.ends_with(')')) {
+            (Some(open), true) => {
+                let displacement = match raw[..open].trim() {
+                    "" => 0,
+                    value => self.signed_immediate(value)?,
+                };
+                let register = self.register(&raw[open + 1..raw.len() - 1])?;
+
+                Ok((displacement, register))
+            }
+            _ => Err(self.error(&format!("expected 'offset(register)', got '{raw}'"))),
+        }
+    }
+
+    /// Parses a value that must fit in the given range, such as a 5-bit operand field
+    fn bounded(&self, raw: &str, min: i64, max: i64) -> Result<i64, CodeParseError> {
+        match self.value(raw)? {
+            value if value >= min && value <= max => Ok(value),
+            _ => Err(self.error(&format!("value '{raw}' is out of range"))),
+        }
+    }
+
+    fn branch_offset(&self, raw: &str) -> Result<i64, CodeParseError> {
+        let target = self.value(raw)?;
+
+        i64::try_from(self.address)
+            .ok()
+            .and_then(|address| target.checked_sub(address))
+            .ok_or_else(|| self.error(&format!("branch target '{raw}' is out of range")))
+    }
+
+    fn conditional_branch(
+        &self,
+        bo: u32,
+        bi: u32,
+        target: &str,
+        link: bool,
+    ) -> Result<u32, CodeParseError> {
+        let offset = self.branch_offset(target)?;
+
+        if offset % 4 != 0 {
+            return Err(self.error("branch target is not aligned to 4 bytes"));
+        }
+
+        if !(-0x8000..0x8000).contains(&offset) {
+            return Err(self.error(&format!(
+                "conditional branch target is out of range ({offset:#x})"
+            )));
+        }
+
+        Ok(16 << 26 | bo << 21 | bi << 16 | (offset as u32 & 0xFFFC) | link as u32)
+    }
+}
+
+fn encode(context: &Context<'_>, line: &str) -> Result<u32, CodeParseError> {
+    let (mnemonic, raw_operands) = match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    };
+
+    let operands: Vec<&str> = match raw_operands {
+        "" => Vec::new(),
+        _ => raw_operands.split(',').map(str::trim).collect(),
+    };
+
+    let expect = |count: usize| -> Result<(), CodeParseError> {
+        match operands.len() == count {
+            true => Ok(()),
+            false => Err(context.error(&format!(
+                "'{mnemonic}' expects {count} operand(s), got {}",
+                operands.len()
+            ))),
+        }
+    };
+
+    let mnemonic = mnemonic.to_ascii_lowercase();
+
+    let word = match mnemonic.as_str() {
+        "nop" => {
+            expect(0)?;
+            NOP
+        }
+        ".long" => {
+            expect(1)?;
+            context.bounded(operands[0], i32::MIN as i64, u32::MAX as i64)? as u32
+        }
+        "li" | "lis" => {
+            expect(2)?;
+            let opcode = if mnemonic == "li" { 14 } else { 15 };
+            let rd = context.register(operands[0])?;
+            let immediate = match mnemonic.as_str() {
+                "li" => context.signed_immediate(operands[1])?,
+                _ => context.high_immediate(operands[1])?,
+            };
+
+            opcode << 26 | rd << 21 | immediate
+        }
+        "addi" | "addis" => {
+            expect(3)?;
+            let opcode = if mnemonic == "addi" { 14 } else { 15 };
+            let rd = context.register(operands[0])?;
+            let ra = context.register(operands[1])?;
+            let immediate = match mnemonic.as_str() {
+                "addi" => context.signed_immediate(operands[2])?,
+                _ => context.high_immediate(operands[2])?,
+            };
+
+            opcode << 26 | rd << 21 | ra << 16 | immediate
+        }
+        "ori" | "oris" => {
+            expect(3)?;
+            let opcode = if mnemonic == "ori" { 24 } else { 25 };
+            let ra = context.register(operands[0])?;
+            let rs = context.register(operands[1])?;
+
+            opcode << 26 | rs << 21 | ra << 16 | context.unsigned_immediate(operands[2])?
+        }
+        "mr" => {
+            expect(2)?;
+            let ra = context.register(operands[0])?;
+            let rs = context.register(operands[1])?;
+
+            31 << 26 | rs << 21 | ra << 16 | rs << 11 | 444 << 1
+        }
+        "add" | "subf" => {
+            expect(3)?;
+            let extended = if mnemonic == "add" { 266 } else { 40 };
+            let rd = context.register(operands[0])?;
+            let ra = context.register(operands[1])?;
+            let rb = context.register(operands[2])?;
+
+            31 << 26 | rd << 21 | ra << 16 | rb << 11 | extended << 1
+        }
+        "mflr" => {
+            expect(1)?;
+            0x7C08_02A6 | context.register(operands[0])? << 21
+        }
+        "mtlr" => {
+            expect(1)?;
+            0x7C08_03A6 | context.register(operands[0])? << 21
+        }
+        "mtctr" => {
+            expect(1)?;
+            0x7C09_03A6 | context.register(operands[0])? << 21
+        }
+        "lbz" | "lhz" | "lwz" | "lwzu" | "stb" | "sth" | "stw" | "stwu" => {
+            expect(2)?;
+            let opcode = match mnemonic.as_str() {
+                "lwz" => 32,
+                "lwzu" => 33,
+                "lbz" => 34,
+                "stw" => 36,
+                "stwu" => 37,
+                "stb" => 38,
+                "lhz" => 40,
+                _ => 44,
+            };
+            let rd = context.register(operands[0])?;
+            let (displacement, ra) = context.memory(operands[1])?;
+
+            opcode << 26 | rd << 21 | ra << 16 | displacement
+        }
+        "lfs" | "lfd" | "stfs" | "stfd" => {
+            expect(2)?;
+            let opcode = match mnemonic.as_str() {
+                "lfs" => 48,
+                "lfd" => 50,
+                "stfs" => 52,
+                _ => 54,
+            };
+            let fd = context.float_register(operands[0])?;
+            let (displacement, ra) = context.memory(operands[1])?;
+
+            opcode << 26 | fd << 21 | ra << 16 | displacement
+        }
+        "ld" | "std" => {
+            expect(2)?;
+            let opcode = if mnemonic == "ld" { 58 } else { 62 };
+            let rd = context.register(operands[0])?;
+            let (displacement, ra) = context.memory(operands[1])?;
+
+            if displacement & 3 != 0 {
+                return Err(context.error("displacement must be a multiple of 4"));
+            }
+
+            opcode << 26 | rd << 21 | ra << 16 | displacement
+        }
+        "cmpwi" | "cmplwi" | "cmpw" | "cmplw" => {
+            let (cr, rest) = match operands.len() {
+                3 => (context.cr_field(operands[0])?, &operands[1..]),
+                2 => (0, &operands[..]),
+                _ => return Err(context.error(&format!("'{mnemonic}' expects 2 or 3 operands"))),
+            };
+            let ra = context.register(rest[0])?;
+
+            match mnemonic.as_str() {
+                "cmpwi" => 11 << 26 | cr << 23 | ra << 16 | context.signed_immediate(rest[1])?,
+                "cmplwi" => 10 << 26 | cr << 23 | ra << 16 | context.unsigned_immediate(rest[1])?,
+                "cmpw" => 31 << 26 | cr << 23 | ra << 16 | context.register(rest[1])? << 11,
+                _ => 31 << 26 | cr << 23 | ra << 16 | context.register(rest[1])? << 11 | 32 << 1,
+            }
+        }
+        "b" | "bl" | "ba" | "bla" => {
+            expect(1)?;
+            let absolute = mnemonic == "ba" || mnemonic == "bla";
+            let link = mnemonic == "bl" || mnemonic == "bla";
+            let offset = match absolute {
+                true => context.value(operands[0])?,
+                false => context.branch_offset(operands[0])?,
+            };
+
+            branch_word(context.line, offset, absolute, link)?
+        }
+        "bc" | "bcl" => {
+            expect(3)?;
+            let bo = context.bounded(operands[0], 0, 0x1F)? as u32;
+            let bi = context.bounded(operands[1], 0, 0x1F)? as u32;
+
+            context.conditional_branch(bo, bi, operands[2], mnemonic == "bcl")?
+        }
+        "beq" | "bne" | "blt" | "bge" | "bgt" | "ble" => {
+            let (cr, target) = match operands.len() {
+                2 => (context.cr_field(operands[0])?, operands[1]),
+                1 => (0, operands[0]),
+                _ => return Err(context.error(&format!("'{mnemonic}' expects 1 or 2 operands"))),
+            };
+
+            // Branch if true (12) or false (4) on the LT (0), GT (1) or EQ (2) bit
+            let (bo, bit) = match mnemonic.as_str() {
+                "blt" => (12, 0),
+                "bge" => (4, 0),
+                "bgt" => (12, 1),
+                "ble" => (4, 1),
+                "beq" => (12, 2),
+                _ => (4, 2),
+            };
+
+            context.conditional_branch(bo, cr * 4 + bit, target, false)?
+        }
+        "bdnz" => {
+            expect(1)?;
+            context.conditional_branch(16, 0, operands[0], false)?
+        }
+        "blr" => {
+            expect(0)?;
+            BLR
+        }
+        "blrl" => {
+            expect(0)?;
+            BLR | 1
+        }
+        "bctr" => {
+            expect(0)?;
+            0x4E80_0420
+        }
+        "bctrl" => {
+            expect(0)?;
+            0x4E80_0421
+        }
+        _ => return Err(context.error(&format!("unknown instruction '{mnemonic}'"))),
+    };
+
+    Ok(word)
+}
+
+/// Parses a decimal or `0x`-prefixed hex number, returning `None` if it does not fit an `i64`
+fn parse_number(raw: &str) -> Option<i64> {
+    if !is_number(raw) {
+        return None;
+    }
+
+    let (negative, digits) = match raw.starts_with('-') {
+        true => (true, &raw[1..]),
+        false => (false, raw),
+    };
+
+    let magnitude = match digits.starts_with("0x") || digits.starts_with("0X") {
+        true => u64::from_str_radix(&digits[2..], 16).ok()?,
+        false => digits.parse::<u64>().ok()?,
+    };
+
+    match negative {
+        true if magnitude == 1 << 63 => Some(i64::MIN),
+        true => i64::try_from(magnitude).ok().map(|value| -value),
+        false => i64::try_from(magnitude).ok(),
+    }
+}
+
+/// Returns whether a value is written as a number, regardless of whether it fits
+fn is_number(raw: &str) -> bool {
+    let digits = match raw.starts_with('-') {
+        true => &raw[1..],
+        false => raw,
+    };
+
+    match digits.starts_with("0x") || digits.starts_with("0X") {
+        true => digits.len() > 2 && digits[2..].chars().all(|c| c.is_ascii_hexdigit()),
+        false => !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()),
+    }
+}
+
+fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '.' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+fn error(line: usize, message: &str) -> CodeParseError {
+    CodeParseError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(source: &str) -> Vec<u32> {
+        assemble_words(source, 0x10000).unwrap()
+    }
+
+    fn error_line(source: &str) -> usize {
+        assemble_words(source, 0x10000).unwrap_err().line
+    }
+
+    #[test]
+    fn encodes_immediates() {
+        assert_eq!(words("li r3, 10"), vec![0x3860_000A]);
+        assert_eq!(words("li r3, -1"), vec![0x3860_FFFF]);
+        assert_eq!(words("lis r4, 0x1234"), vec![0x3C80_1234]);
+        assert_eq!(words("ori r4, r4, 0x5678"), vec![0x6084_5678]);
+        assert_eq!(words("addi r3, r1, 8"), vec![0x3861_0008]);
+        assert_eq!(words("addi r3, r3, -1"), vec![0x3863_FFFF]);
+        assert_eq!(error_line("nop\nli r3, 0x8000"), 2);
+    }
+
+    #[test]
+    fn splits_values_with_suffixes() {
+        assert_eq!(
+            words("lis r3, 0x12348000@ha\naddi r3, r3, 0x12348000@l"),
+            vec![0x3C60_1235, 0x3863_8000]
+        );
+        assert_eq!(
+            words("lis r3, 0x12348000@h\nori r3, r3, 0x12348000@l"),
+            vec![0x3C60_1234, 0x6063_8000]
+        );
+        assert_eq!(
+            words("lis r3, data@ha\naddi r3, r3, data@l\ndata:\n.long 0"),
+            vec![0x3C60_0001, 0x3863_0008, 0]
+        );
+    }
+
+    #[test]
+    fn encodes_registers_and_comparisons() {
+        assert_eq!(words("mr r3, r4"), vec![0x7C83_2378]);
+        assert_eq!(words("cmpwi r3, 0"), vec![0x2C03_0000]);
+        assert_eq!(words("cmpwi cr7, r3, 5"), vec![0x2F83_0005]);
+    }
+
+    #[test]
+    fn encodes_loads_and_stores() {
+        assert_eq!(words("lwz r3, 8(r1)"), vec![0x8061_0008]);
+        assert_eq!(words("stw r0, -4(r1)"), vec![0x9001_FFFC]);
+    }
+
+    #[test]
+    fn resolves_forward_and_backward_labels() {
+        assert_eq!(words("b end\nnop\nend: blr"), vec![0x4800_0008, NOP, BLR]);
+        assert_eq!(words("top: nop\nb top"), vec![NOP, 0x4BFF_FFFC]);
+        assert_eq!(words("bl end\nend: blr"), vec![0x4800_0005, BLR]);
+        assert_eq!(
+            words("bc 12, 2, end\nnop\nend: blr"),
+            vec![0x4182_0008, NOP, BLR]
+        );
+        assert_eq!(words("top: nop\nnop\nbne top"), vec![NOP, NOP, 0x4082_FFF8]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_branches() {
+        assert_eq!(error_line("nop\nb 0x4000000"), 2);
+        assert_eq!(error_line("bc 12, 2, 0x20000"), 1);
+        assert_eq!(error_line("beq 0x10002"), 1);
+        assert!(encode_branch(0x10000, 0x10000 + 0x0200_0000, false).is_err());
+        assert_eq!(encode_branch(0x10000, 0x10010, true).unwrap(), 0x4800_0011);
+    }
+
+    #[test]
+    fn rejects_out_of_range_literals() {
+        assert_eq!(error_line("li r3, -0x8000000000000000"), 1);
+        assert_eq!(error_line("li r3, 0x10000000000000000"), 1);
+        assert_eq!(error_line("li r3, 99999999999999999999"), 1);
+        assert_eq!(error_line("nop\n.long 0x100000000"), 2);
+        assert_eq!(error_line(".long -0x80000001"), 1);
+        assert_eq!(error_line("bc 32, 2, 0x10000"), 1);
+        assert_eq!(error_line("bc 12, -1, 0x10000"), 1);
+        assert_eq!(error_line("b 0x7FFFFFFFFFFFFFFF"), 1);
+        assert_eq!(error_line("b -0x8000000000000000"), 1);
+        assert_eq!(words("lis r3, 0x7FFFFFFFFFFFFFFF@ha"), vec![0x3C60_0000]);
+
+        assert!(
+            assemble_words("b 0x10000", u64::MAX - 3).is_err(),
+            "branch from an address beyond i64"
+        );
+        assert!(assemble_words("nop\nnop", u64::MAX - 3).is_err());
+        assert!(assemble_words("nop\nend: blr", u64::MAX - 3).is_err());
+
+        assert_eq!(parse_number("-0x8000000000000000"), Some(i64::MIN));
+        assert_eq!(parse_number("0x7FFFFFFFFFFFFFFF"), Some(i64::MAX));
+        assert_eq!(parse_number("0x8000000000000000"), None);
+        assert_eq!(parse_number("--1"), None);
+        assert_eq!(words(".long 0xFFFFFFFF\n.long -1"), vec![0xFFFF_FFFF; 2]);
+    }
+
+    #[test]
+    fn computes_size_without_resolving_operands() {
+        assert_eq!(
+            assembled_size("start:\n  bl far # call\n\n  blr").unwrap(),
+            8
+        );
+        assert_eq!(assembled_size("; only a comment").unwrap(), 0);
+        assert!(assembled_size("1bad: nop").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_labels_and_mnemonics() {
+        assert_eq!(error_line("b missing"), 1);
+        assert_eq!(error_line("nop\n\nfrob r3"), 3);
+    }
+}
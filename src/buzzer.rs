@@ -0,0 +1,197 @@
+//! Playing sequences of buzzer rings in the background
+
+use crate::worker::{StopSignal, Worker};
+use crate::{BuzzerType, CCAPI};
+use anyhow::{bail, ensure, Result};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Longest delay accepted in seconds, far beyond any useful pattern
+const MAX_DELAY_SECS: f64 = u32::MAX as f64;
+
+/// A single step of a [BuzzerPattern]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuzzerStep {
+    Ring(BuzzerType),
+    Delay(Duration),
+}
+
+impl FromStr for BuzzerStep {
+    type Err = anyhow::Error;
+
+    /// Parses a buzzer type such as `double`, or a delay such as `500ms` or `1.5s`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Ok(buzzer_type) = BuzzerType::from_str(s) {
+            return Ok(BuzzerStep::Ring(buzzer_type));
+        }
+
+        if s.ends_with("ms") {
+            if let Ok(millis) = s[..s.len() - 2].parse::<u64>() {
+                return Ok(BuzzerStep::Delay(Duration::from_millis(millis)));
+            }
+        } else if s.ends_with('s') {
+            if let Ok(secs) = s[..s.len() - 1].parse::<f64>() {
+                // Bounded so the conversion below cannot overflow a Duration and panic
+                if (0.0..=MAX_DELAY_SECS).contains(&secs) {
+                    return Ok(BuzzerStep::Delay(Duration::from_secs_f64(secs)));
+                }
+            }
+        }
+
+        bail!("invalid buzzer pattern step '{s}' provided")
+    }
+}
+
+/// A sequence of rings and delays played on a background thread
+///
+/// The console ignores a ring requested while another one is still sounding, so rings
+/// should be separated by delays long enough for each one to finish.
+///
+/// ```no_run
+/// use ccapi::buzzer::BuzzerPattern;
+/// use ccapi::CCAPI;
+/// use std::net::Ipv4Addr;
+///
+/// let ccapi = CCAPI::new(Ipv4Addr::new(192, 168, 1, 100));
+///
+/// // Build failed
+/// let pattern: BuzzerPattern = "double,700ms,double,700ms,double".parse().unwrap();
+/// pattern.play(&ccapi).unwrap().wait().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuzzerPattern {
+    pub steps: Vec<BuzzerStep>,
+}
+
+impl FromStr for BuzzerPattern {
+    type Err = anyhow::Error;
+
+    /// Parses comma separated steps, such as `single,500ms,double`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split(',')
+            .map(BuzzerStep::from_str)
+            .collect::<Result<Vec<BuzzerStep>>>()?;
+
+        Ok(BuzzerPattern { steps })
+    }
+}
+
+impl BuzzerPattern {
+    pub fn new(steps: Vec<BuzzerStep>) -> Self {
+        BuzzerPattern { steps }
+    }
+
+    /// Starts playing the pattern on a background thread
+    pub fn play(self, ccapi: &CCAPI) -> Result<BuzzerHandle> {
+        ensure!(
+            self.steps
+                .iter()
+                .any(|step| matches!(step, BuzzerStep::Ring(_))),
+            "buzzer pattern has no rings"
+        );
+
+        let ccapi = ccapi.clone();
+        let worker = Worker::spawn(move |signal| self.play_steps(&ccapi, signal));
+
+        Ok(BuzzerHandle { worker })
+    }
+
+    fn play_steps(&self, ccapi: &CCAPI, signal: &StopSignal) -> Result<()> {
+        for step in &self.steps {
+            match step {
+                BuzzerStep::Ring(buzzer_type) => ccapi.ring_buzzer(*buzzer_type)?,
+                BuzzerStep::Delay(delay) => {
+                    if !signal.sleep(*delay) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if signal.is_stopped() {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Handle to a playing [BuzzerPattern](crate::buzzer::BuzzerPattern)
+pub struct BuzzerHandle {
+    worker: Worker,
+}
+
+impl BuzzerHandle {
+    /// Returns whether the pattern is still playing
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Waits for the rest of the pattern to play
+    pub fn wait(mut self) -> Result<()> {
+        self.worker.join()
+    }
+
+    /// Cancels the remaining steps, returning the error that ended playback early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(s: &str) -> BuzzerStep {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_rings_and_delays() {
+        assert_eq!(step("single"), BuzzerStep::Ring(BuzzerType::Single));
+        assert_eq!(step(" triple "), BuzzerStep::Ring(BuzzerType::Triple));
+        assert_eq!(step("500ms"), BuzzerStep::Delay(Duration::from_millis(500)));
+        assert_eq!(step("0ms"), BuzzerStep::Delay(Duration::from_millis(0)));
+        assert_eq!(step("1.5s"), BuzzerStep::Delay(Duration::from_millis(1500)));
+        assert_eq!(step("2s"), BuzzerStep::Delay(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        for s in &["", "quadruple", "500", "ms", "s", "-5ms", "1.5ms", "fast"] {
+            assert!(s.parse::<BuzzerStep>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn rejects_delays_too_long_or_not_finite() {
+        assert_eq!(
+            step("4294967295s"),
+            BuzzerStep::Delay(Duration::from_secs(u32::MAX as u64))
+        );
+
+        for s in &["4294967296s", "1e20s", "-1s", "NaNs", "infs", "-infs"] {
+            assert!(s.parse::<BuzzerStep>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parses_comma_separated_patterns() {
+        let pattern: BuzzerPattern = "double, 700ms,single,0.25s".parse().unwrap();
+        assert_eq!(
+            pattern,
+            BuzzerPattern::new(vec![
+                BuzzerStep::Ring(BuzzerType::Double),
+                BuzzerStep::Delay(Duration::from_millis(700)),
+                BuzzerStep::Ring(BuzzerType::Single),
+                BuzzerStep::Delay(Duration::from_millis(250)),
+            ])
+        );
+
+        assert!("single,,double".parse::<BuzzerPattern>().is_err());
+        assert!("single,1e20s".parse::<BuzzerPattern>().is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{MemoryRegion, MEMORY_CHUNK_SIZE};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Size of a single cached page of process memory
+pub const CACHE_PAGE_SIZE: u64 = MEMORY_CHUNK_SIZE as u64;
+
+/// Most pages kept at once (16MB), the oldest ones are evicted first
+pub const MAX_CACHED_PAGES: usize = 0x1000;
+
+/// Hit and miss counters of a [ReadCache](crate::cache::ReadCache), counted per page
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the fraction of page lookups served from the cache
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+struct CachedPage {
+    data: Vec<u8>,
+    fetched: Instant,
+}
+
+/// Page-based cache for process memory reads
+///
+/// Enabled with [CCAPI::enable_read_cache](crate::CCAPI::enable_read_cache), after which
+/// reads are served from pages fetched less than the time-to-live ago. Writes made through
+/// the same client (or its clones) invalidate the pages they touch. Expired pages are
+/// dropped when looked up, and at most [MAX_CACHED_PAGES] are kept.
+pub struct ReadCache {
+    ttl: Duration,
+    pages: Mutex<HashMap<(u32, u64), CachedPage>>,
+    /// Bumped on every invalidation, only while the `pages` lock is held
+    epoch: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ReadCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        ReadCache {
+            ttl,
+            pages: Mutex::new(HashMap::new()),
+            epoch: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns how long a fetched page is served from the cache
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Reads memory from cached pages, calling `fetch` with the address of each missing page
+    pub(crate) fn read<F>(&self, pid: u32, address: u64, size: u32, mut fetch: F) -> Result<Vec<u8>>
+    where
+        F: FnMut(u64) -> Result<Vec<u8>>,
+    {
+        let end = address + size as u64;
+        let mut memory = Vec::with_capacity(size as usize);
+        let mut page = address - address % CACHE_PAGE_SIZE;
+
+        while page < end {
+            let start = address.max(page) - page;
+            let stop = end.min(page + CACHE_PAGE_SIZE) - page;
+
+            let mut pages = self.pages.lock().unwrap();
+
+            match pages.get(&(pid, page)) {
+                Some(cached) if cached.fetched.elapsed() < self.ttl => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    memory.extend_from_slice(&cached.data[start as usize..stop as usize]);
+                }
+                cached => {
+                    if cached.is_some() {
+                        pages.remove(&(pid, page));
+                    }
+
+                    // Don't hold the lock during the request
+                    let epoch = self.epoch.load(Ordering::SeqCst);
+                    drop(pages);
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+
+                    let data = fetch(page)?;
+                    memory.extend_from_slice(&data[start as usize..stop as usize]);
+
+                    let fetched = Instant::now();
+                    let mut pages = self.pages.lock().unwrap();
+
+                    // A write during the request may have changed the page after it was read
+                    if self.epoch.load(Ordering::SeqCst) == epoch {
+                        self.make_room(&mut pages);
+                        pages.insert((pid, page), CachedPage { data, fetched });
+                    }
+                }
+            }
+
+            page += CACHE_PAGE_SIZE;
+        }
+
+        Ok(memory)
+    }
+
+    /// Evicts pages until another one fits, starting with expired pages and then the oldest
+    fn make_room(&self, pages: &mut HashMap<(u32, u64), CachedPage>) {
+        if pages.len() < MAX_CACHED_PAGES {
+            return;
+        }
+
+        let ttl = self.ttl;
+        pages.retain(|_, cached| cached.fetched.elapsed() < ttl);
+
+        while pages.len() >= MAX_CACHED_PAGES {
+            let oldest = pages
+                .iter()
+                .min_by_key(|(_, cached)| cached.fetched)
+                .map(|(key, _)| *key);
+
+            match oldest {
+                Some(key) => pages.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Drops all cached pages overlapping the given range of a process
+    pub fn invalidate(&self, pid: u32, range: MemoryRegion) {
+        let mut pages = self.pages.lock().unwrap();
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+
+        pages.retain(|(page_pid, page), _| {
+            *page_pid != pid || *page >= range.end() || page + CACHE_PAGE_SIZE <= range.address
+        });
+    }
+
+    /// Drops all cached pages of a process
+    pub fn invalidate_process(&self, pid: u32) {
+        let mut pages = self.pages.lock().unwrap();
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+
+        pages.retain(|(page_pid, _), _| *page_pid != pid);
+    }
+
+    /// Drops every cached page
+    pub fn clear(&self) {
+        let mut pages = self.pages.lock().unwrap();
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+
+        pages.clear();
+    }
+
+    /// Returns the hit and miss counters
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the hit and miss counters to zero
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for ReadCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadCache")
+            .field("ttl", &self.ttl)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn page(address: u64) -> Vec<u8> {
+        (0..CACHE_PAGE_SIZE)
+            .map(|offset| ((address + offset) >> 4) as u8)
+            .collect()
+    }
+
+    fn cached_keys(cache: &ReadCache) -> Vec<(u32, u64)> {
+        let mut keys: Vec<_> = cache.pages.lock().unwrap().keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn counts_hits_and_misses_per_page() {
+        let cache = ReadCache::new(Duration::from_secs(60));
+        let fetches = Cell::new(0);
+        let fetch = |address| {
+            fetches.set(fetches.get() + 1);
+            Ok(page(address))
+        };
+
+        let memory = cache.read(1, 0x10FF0, 0x20, fetch).unwrap();
+        assert_eq!(
+            memory,
+            page(0x10000)[0xFF0..]
+                .iter()
+                .chain(&page(0x11000)[..0x10])
+                .copied()
+                .collect::<Vec<u8>>()
+        );
+        assert_eq!(fetches.get(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+
+        cache.read(1, 0x11000, 4, fetch).unwrap();
+        assert_eq!(fetches.get(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+
+        // Pages are cached per process
+        cache.read(2, 0x11000, 4, fetch).unwrap();
+        assert_eq!(fetches.get(), 3);
+        assert_eq!(cache.stats().hit_ratio(), 0.25);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn refetches_and_drops_expired_pages() {
+        let cache = ReadCache::new(Duration::from_secs(0));
+        let fetches = Cell::new(0);
+        let fetch = |address| {
+            fetches.set(fetches.get() + 1);
+            Ok(page(address))
+        };
+
+        cache.read(1, 0x10000, 4, fetch).unwrap();
+        cache.read(1, 0x10000, 4, fetch).unwrap();
+
+        assert_eq!(fetches.get(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+        assert_eq!(cached_keys(&cache).len(), 1);
+    }
+
+    #[test]
+    fn invalidates_only_overlapping_pages_of_the_same_process() {
+        let cache = ReadCache::new(Duration::from_secs(60));
+
+        cache
+            .read(1, 0x10000, 0x3000, |address| Ok(page(address)))
+            .unwrap();
+        cache
+            .read(2, 0x11000, 4, |address| Ok(page(address)))
+            .unwrap();
+
+        cache.invalidate(1, MemoryRegion::new(0x11FFC, 4));
+        assert_eq!(
+            cached_keys(&cache),
+            vec![(1, 0x10000), (1, 0x12000), (2, 0x11000)]
+        );
+
+        cache.invalidate(1, MemoryRegion::new(0x12FFC, 8));
+        assert_eq!(cached_keys(&cache), vec![(1, 0x10000), (2, 0x11000)]);
+
+        cache.invalidate_process(1);
+        assert_eq!(cached_keys(&cache), vec![(2, 0x11000)]);
+
+        cache.clear();
+        assert!(cached_keys(&cache).is_empty());
+    }
+
+    #[test]
+    fn skips_caching_pages_invalidated_during_the_fetch() {
+        let cache = ReadCache::new(Duration::from_secs(60));
+
+        cache
+            .read(1, 0x10000, 4, |address| {
+                cache.invalidate(1, MemoryRegion::new(address, 4));
+                Ok(page(address))
+            })
+            .unwrap();
+
+        assert!(cached_keys(&cache).is_empty());
+    }
+
+    #[test]
+    fn evicts_the_oldest_pages_when_full() {
+        let cache = ReadCache::new(Duration::from_secs(60));
+        let size = (MAX_CACHED_PAGES as u64 + 1) * CACHE_PAGE_SIZE;
+
+        for start in (0..size).step_by(MEMORY_CHUNK_SIZE as usize) {
+            cache
+                .read(1, start, MEMORY_CHUNK_SIZE, |address| Ok(page(address)))
+                .unwrap();
+        }
+
+        let keys = cached_keys(&cache);
+        assert_eq!(keys.len(), MAX_CACHED_PAGES);
+        assert!(!keys.contains(&(1, 0)));
+        assert!(keys.contains(&(1, size - CACHE_PAGE_SIZE)));
+    }
+}
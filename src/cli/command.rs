@@ -1,7 +1,14 @@
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
-use ccapi::{BuzzerType, NotifyIcon, ShutdownMode, CCAPI, ConsoleLed, LedStatus};
+use ccapi::buzzer::BuzzerPattern;
+use ccapi::diff::{self, SnapshotDiff};
+use ccapi::disassembler;
+use ccapi::elf::{Elf, SymbolMap};
+use ccapi::progress::ProgressReporter;
+use ccapi::regions;
+use ccapi::snapshot::{self, Snapshot};
+use ccapi::{BuzzerType, ConsoleLed, LedStatus, MemoryRegion, NotifyIcon, ShutdownMode, CCAPI};
 use getopts::Matches;
 
 pub fn run(ccapi: &CCAPI, matches: &Matches) -> Result<()> {
@@ -9,14 +16,19 @@ pub fn run(ccapi: &CCAPI, matches: &Matches) -> Result<()> {
 
     let first_free = matches.free.get(0);
     let second_free = matches.free.get(1);
+    let third_free = matches.free.get(2);
 
     match cmd.as_ref() {
         "ringbuzzer" => match first_free {
+            Some(raw_pattern) if raw_pattern.contains(',') => {
+                let pattern = BuzzerPattern::from_str(raw_pattern)?;
+                pattern.play(ccapi)?.wait()?;
+            }
             Some(raw_buzzer_type) => {
                 let buzzer_type = BuzzerType::from_str(&raw_buzzer_type)?;
                 ccapi.ring_buzzer(buzzer_type)?;
             }
-            _ => bail!("A buzzer type must be provided"),
+            _ => bail!("A buzzer type or pattern must be provided"),
         },
         "shutdown" => ccapi.shutdown(ShutdownMode::Shutdown)?,
         "restart" => match first_free {
@@ -58,7 +70,24 @@ pub fn run(ccapi: &CCAPI, matches: &Matches) -> Result<()> {
                     }
                     _ => bail!("A valid process id must be specified"),
                 },
-                "map" => println!("{:?}", &ccapi.get_process_map()?),
+                "map" => {
+                    let process_map = ccapi.get_process_map()?;
+                    println!("{:?}", process_map.names);
+
+                    if !process_map.failed.is_empty() {
+                        eprintln!("Could not look up names of pids {:?}", process_map.failed);
+                    }
+                }
+                "regions" => match second_free {
+                    Some(raw_pid) => {
+                        let pid: u32 = raw_pid.parse()?;
+
+                        for region in ccapi.region_prober().probe(ccapi, pid)? {
+                            println!("{:#010x}-{:#010x}", region.address, region.end());
+                        }
+                    }
+                    _ => bail!("A valid process id must be specified"),
+                },
                 _ => bail!("Invalid action '{action}' specified for processes"),
             },
             _ => bail!("A valid action for processes must be specified"),
@@ -71,8 +100,151 @@ pub fn run(ccapi: &CCAPI, matches: &Matches) -> Result<()> {
             }
             _ => bail!("A valid icon and message must be provided"),
         },
+        "dump" => match (first_free, second_free) {
+            (Some(raw_pid), Some(path)) => {
+                let pid: u32 = raw_pid.parse()?;
+                let requested = matches.free[2..]
+                    .iter()
+                    .map(|raw_region| MemoryRegion::from_str(raw_region))
+                    .collect::<Result<Vec<_>>>()?;
+
+                // Only mapped memory is dumped, every mapped region when none were requested
+                let regions = match requested.is_empty() {
+                    true => ccapi.region_prober().regions(ccapi, pid)?,
+                    false => {
+                        let mut regions = Vec::new();
+
+                        for region in &requested {
+                            regions.extend(regions::mapped_parts(ccapi, pid, region)?);
+                        }
+
+                        regions
+                    }
+                };
+
+                if regions.is_empty() {
+                    bail!("None of the requested memory is mapped in process '{pid}'");
+                }
+
+                let snapshot = match matches.opt_present("progress") {
+                    true => {
+                        let mut reporter = ProgressReporter::new(ccapi, "Dumping").terminal(true);
+                        let snapshot = Snapshot::capture_with_progress(
+                            ccapi,
+                            pid,
+                            &regions,
+                            reporter.callback(),
+                        )?;
+                        snapshot.save(path)?;
+                        snapshot
+                    }
+                    false => snapshot::dump(ccapi, pid, &regions, path)?,
+                };
+                println!(
+                    "Dumped {} region(s) of '{}' to '{path}'",
+                    snapshot.regions.len(),
+                    snapshot.process_name
+                );
+            }
+            _ => bail!("A valid process id and output file must be provided"),
+        },
+        "restore" => match (first_free, second_free) {
+            (Some(raw_pid), Some(path)) => {
+                let pid: u32 = raw_pid.parse()?;
+                let snapshot = match matches.opt_present("progress") {
+                    true => {
+                        let mut reporter = ProgressReporter::new(ccapi, "Restoring").terminal(true);
+                        let snapshot = Snapshot::load(path)?;
+                        snapshot.restore_with_progress(ccapi, pid, reporter.callback())?;
+                        snapshot
+                    }
+                    false => snapshot::restore(ccapi, pid, path)?,
+                };
+                println!(
+                    "Restored {} region(s) of '{}' from '{path}'",
+                    snapshot.regions.len(),
+                    snapshot.process_name
+                );
+            }
+            _ => bail!("A valid process id and snapshot file must be provided"),
+        },
+        "diff" => match (first_free, second_free) {
+            (Some(first), Some(second)) => {
+                // A numeric first argument compares live process memory against a snapshot
+                let snapshot_diff = match first.parse::<u32>() {
+                    Ok(pid) => diff::diff_live(ccapi, pid, &Snapshot::load(second)?)?,
+                    Err(_) => {
+                        diff::diff_snapshots(&Snapshot::load(first)?, &Snapshot::load(second)?)
+                    }
+                };
+
+                match matches.opt_present("json") {
+                    true => println!("{}", serde_json::to_string_pretty(&snapshot_diff)?),
+                    false => print_diff(&snapshot_diff),
+                }
+            }
+            _ => bail!("Two snapshot files, or a process id and a snapshot file must be provided"),
+        },
+        "disasm" => match (first_free, second_free, third_free) {
+            (Some(raw_pid), Some(raw_address), Some(raw_count)) => {
+                let pid: u32 = raw_pid.parse()?;
+                let address = u64::from_str_radix(raw_address.trim_start_matches("0x"), 16)?;
+                let count: u32 = raw_count.parse()?;
+                let mut instructions =
+                    disassembler::disassemble_process(ccapi, pid, address, count)?;
+
+                if let Some(path) = matches.opt_str("symbols") {
+                    let symbols = SymbolMap::from_elf(&Elf::load(path)?)?;
+                    disassembler::annotate(&mut instructions, &symbols);
+                }
+
+                match matches.opt_present("json") {
+                    true => println!("{}", serde_json::to_string_pretty(&instructions)?),
+                    false => instructions
+                        .iter()
+                        .for_each(|instruction| println!("{instruction}")),
+                }
+            }
+            _ => bail!("A valid process id, hex address and instruction count must be provided"),
+        },
         _ => bail!("Command '{cmd}' not recognized"),
     }
 
     Ok(())
 }
+
+fn print_diff(snapshot_diff: &SnapshotDiff) {
+    for region_diff in &snapshot_diff.regions {
+        let region = &region_diff.region;
+        println!(
+            "Region {:#010x} ({:#x} bytes): {} change(s)",
+            region.address,
+            region.size,
+            region_diff.changes.len()
+        );
+        println!(
+            "  {:<12}{:<10}{:<10}{:<16}{:<16}",
+            "ADDRESS", "OLD U32", "NEW U32", "OLD F32", "NEW F32"
+        );
+
+        for word in region_diff.changes.iter().flat_map(|change| &change.words) {
+            println!(
+                "  {:<12}{:<10}{:<10}{:<16}{:<16}",
+                format!("{:#010x}", word.address),
+                format!("{:08X}", word.old_u32),
+                format!("{:08X}", word.new_u32),
+                word.old_f32,
+                word.new_f32
+            );
+        }
+    }
+
+    for region in &snapshot_diff.missing {
+        println!(
+            "Region {:#010x} missing from second snapshot",
+            region.address
+        );
+    }
+
+    println!("{} byte(s) changed", snapshot_diff.changed_bytes());
+}
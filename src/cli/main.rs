@@ -12,6 +12,14 @@ fn main() -> Result<()> {
     let mut opts = Options::new();
     opts.reqopt("i", "ip-address", "Console IPv4 address", "");
     opts.reqopt("c", "command", "Command", "");
+    opts.optflag("j", "json", "Print output as JSON");
+    opts.optflag("p", "progress", "Show progress on the console screen");
+    opts.optopt(
+        "s",
+        "symbols",
+        "ELF file used to name disassembled addresses",
+        "FILE",
+    );
 
     let matches = opts.parse(&args[1..])?;
 
@@ -0,0 +1,586 @@
+//! Per-title patch database keyed by title ID and version
+//!
+//! Databases are stored as JSON:
+//!
+//! ```json
+//! {
+//!   "BLUS30001": {
+//!     "name": "Example Game",
+//!     "versions": {
+//!       "*": [
+//!         { "name": "No recoil", "address": "0x0012A4B0", "bytes": "60000000" }
+//!       ],
+//!       "01.02": [
+//!         { "name": "Infinite ammo", "pointer": ["0x01C8F140", "0x20", "0x4C"], "bytes": "000003E7" },
+//!         { "name": "Skip intro", "signature": "38 60 ?? 01 4E 80 00 20", "offset": 2, "bytes": "00" }
+//!       ]
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Entries under the `*` version apply to every version of a title.
+
+use crate::game::GameInfoResolver;
+use crate::patch::PatchSet;
+use crate::regions::read_mapped;
+use crate::{MemoryRegion, CCAPI};
+use anyhow::{bail, Context, Result};
+use serde::{de, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+/// Version key of entries applying to every version of a title
+pub const ANY_VERSION: &str = "*";
+
+const DEFAULT_SCAN_START: u64 = 0x0001_0000;
+const DEFAULT_SCAN_END: u64 = 0x0200_0000;
+
+/// Where a patch is written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PatchTarget {
+    /// A fixed address
+    Address {
+        #[serde(with = "hex_number")]
+        address: u64,
+    },
+    /// A chain of 32-bit pointers, starting at the first element, with an offset
+    /// added after each dereference
+    Pointer {
+        #[serde(with = "hex_numbers")]
+        pointer: Vec<u64>,
+    },
+    /// The first match of a byte pattern (`??` matching any byte) plus an offset
+    Signature {
+        signature: String,
+        #[serde(default)]
+        offset: i64,
+        #[serde(
+            default,
+            with = "hex_number_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        start: Option<u64>,
+        #[serde(
+            default,
+            with = "hex_number_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        end: Option<u64>,
+    },
+}
+
+/// A named patch within the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(flatten)]
+    pub target: PatchTarget,
+    #[serde(with = "crate::hex")]
+    pub bytes: Vec<u8>,
+}
+
+impl PatchEntry {
+    /// Resolves the address the patch is written to in a running process
+    pub fn resolve(&self, ccapi: &CCAPI, pid: u32) -> Result<u64> {
+        match &self.target {
+            PatchTarget::Address { address } => Ok(*address),
+            PatchTarget::Pointer { pointer } => resolve_pointer(ccapi, pid, pointer),
+            PatchTarget::Signature {
+                signature,
+                offset,
+                start,
+                end,
+            } => {
+                let start = start.unwrap_or(DEFAULT_SCAN_START);
+                let end = end.unwrap_or(DEFAULT_SCAN_END);
+
+                if end <= start {
+                    bail!("scan range '{start:#x}'-'{end:#x}' of signature '{signature}' is empty");
+                }
+
+                let region = MemoryRegion::new(start, end - start);
+
+                let address = find_signature(ccapi, pid, &region, signature)?
+                    .with_context(|| format!("signature '{signature}' not found"))?;
+
+                match (address as i64).checked_add(*offset) {
+                    Some(patched) if patched >= 0 => Ok(patched as u64),
+                    _ => bail!(
+                        "offset '{offset}' from signature match '{address:#x}' is out of range"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// All known patches of a single title
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TitleEntry {
+    #[serde(default)]
+    pub name: String,
+    /// Patches keyed by title version (`APP_VER`), or [ANY_VERSION]
+    pub versions: BTreeMap<String, Vec<PatchEntry>>,
+}
+
+/// Patches for many titles, keyed by title ID (e.g. `BLUS30001`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PatchDatabase {
+    pub titles: BTreeMap<String, TitleEntry>,
+}
+
+impl PatchDatabase {
+    /// Parses a database from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the database to JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Loads a database from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)?;
+
+        PatchDatabase::from_json(&json).with_context(|| format!("invalid patch database {path:?}"))
+    }
+
+    /// Returns the entries for a title version, including those for any version
+    ///
+    /// Without a version, the entries of the only version listed are used. Fails if
+    /// the title lists more than one version.
+    pub fn entries(&self, title_id: &str, version: Option<&str>) -> Result<Vec<PatchEntry>> {
+        let title = match self.titles.get(title_id) {
+            Some(title) => title,
+            None => return Ok(Vec::new()),
+        };
+
+        let specific: Vec<&String> = title
+            .versions
+            .keys()
+            .filter(|v| *v != ANY_VERSION)
+            .collect();
+
+        let version = match (version, specific.as_slice()) {
+            (Some(version), _) => Some(version),
+            (None, []) => None,
+            (None, [only]) => Some(only.as_str()),
+            (None, _) => {
+                bail!("title '{title_id}' has patches for several versions, one must be chosen")
+            }
+        };
+
+        let mut entries = title.versions.get(ANY_VERSION).cloned().unwrap_or_default();
+
+        if let Some(patches) = version.and_then(|version| title.versions.get(version)) {
+            entries.extend(patches.iter().cloned());
+        }
+
+        Ok(entries)
+    }
+
+    /// Finds the running game and returns the patches available for its version
+    ///
+    /// The game is the first process whose title ID, as resolved by `resolver`, is listed in
+    /// the database. Its version is the `APP_VER` of the `PARAM.SFO` the resolver holds for
+    /// that title (or for the disc). CCAPI cannot read files from the console, so the
+    /// metadata has to be added to the resolver beforehand. When the version is unknown,
+    /// the entries of the only version listed are used, see
+    /// [entries](crate::database::PatchDatabase::entries).
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console running the game
+    /// * `resolver` - Metadata of the installed titles and inserted disc
+    pub fn for_running_game(
+        &self,
+        ccapi: &CCAPI,
+        resolver: &GameInfoResolver,
+    ) -> Result<GamePatches> {
+        for process in ccapi.get_processes()? {
+            let game = resolver.resolve_path(process.pid, &process.path);
+
+            if let Some(title_id) = game.title_id {
+                if self.titles.contains_key(&title_id) {
+                    let patches = self
+                        .entries(&title_id, game.version.as_deref())?
+                        .into_iter()
+                        .map(|entry| GamePatch {
+                            entry,
+                            applied: None,
+                        })
+                        .collect();
+
+                    return Ok(GamePatches {
+                        pid: process.pid,
+                        title_id,
+                        patches,
+                    });
+                }
+            }
+        }
+
+        bail!("No running process matches a title in the patch database")
+    }
+}
+
+/// A database entry that can be toggled on a running game
+#[derive(Debug)]
+pub struct GamePatch {
+    pub entry: PatchEntry,
+    applied: Option<PatchSet>,
+}
+
+impl GamePatch {
+    pub fn is_enabled(&self) -> bool {
+        self.applied.is_some()
+    }
+}
+
+/// The patches available for a running game
+#[derive(Debug)]
+pub struct GamePatches {
+    pub pid: u32,
+    pub title_id: String,
+    pub patches: Vec<GamePatch>,
+}
+
+impl GamePatches {
+    /// Enables or disables a patch by name
+    ///
+    /// Enabling writes the patch and remembers the original bytes, disabling restores them.
+    pub fn set_enabled(&mut self, ccapi: &CCAPI, name: &str, enabled: bool) -> Result<()> {
+        let pid = self.pid;
+        let title_id = &self.title_id;
+        let patch = self
+            .patches
+            .iter_mut()
+            .find(|patch| patch.entry.name == name)
+            .with_context(|| format!("No patch named '{name}' for title '{title_id}'"))?;
+
+        match (enabled, patch.applied.take()) {
+            (true, None) => {
+                let address = patch.entry.resolve(ccapi, pid)?;
+                let mut patch_set = PatchSet::new(name).patch(address, &patch.entry.bytes);
+
+                patch_set.apply(ccapi, pid)?;
+                patch.applied = Some(patch_set);
+            }
+            (false, Some(mut patch_set)) => {
+                if let Err(e) = patch_set.revert(ccapi) {
+                    patch.applied = Some(patch_set);
+                    return Err(e);
+                }
+            }
+            (_, applied) => patch.applied = applied,
+        }
+
+        Ok(())
+    }
+
+    /// Returns the names of all enabled patches
+    pub fn enabled(&self) -> Vec<&str> {
+        self.patches
+            .iter()
+            .filter(|patch| patch.is_enabled())
+            .map(|patch| patch.entry.name.as_str())
+            .collect()
+    }
+}
+
+fn resolve_pointer(ccapi: &CCAPI, pid: u32, pointer: &[u64]) -> Result<u64> {
+    let (base, offsets) = match pointer.split_first() {
+        Some(split) => split,
+        None => bail!("pointer path is empty"),
+    };
+
+    let mut address = *base;
+
+    for offset in offsets {
+        let memory = ccapi.read_process_memory(&pid, &address, &4)?;
+        let value = u32::from_be_bytes(memory.as_slice().try_into()?) as u64;
+
+        if value == 0 {
+            bail!("pointer at '{address:#x}' is null");
+        }
+
+        address = match value.checked_add(*offset) {
+            Some(address) => address,
+            None => bail!("offset '{offset:#x}' from pointer '{value:#x}' overflows"),
+        };
+    }
+
+    Ok(address)
+}
+
+/// Returns the address of the first match of a pattern such as `38 60 ?? 01`
+///
+/// Only the mapped parts of the region are read, so unmapped pages are skipped.
+pub fn find_signature(
+    ccapi: &CCAPI,
+    pid: u32,
+    region: &MemoryRegion,
+    signature: &str,
+) -> Result<Option<u64>> {
+    let pattern = parse_signature(signature)?;
+
+    if pattern.is_empty() {
+        bail!("signature is empty");
+    }
+
+    for (address, memory) in read_mapped(ccapi, pid, region)? {
+        let found = memory.windows(pattern.len()).position(|window| {
+            window
+                .iter()
+                .zip(&pattern)
+                .all(|(byte, expected)| expected.map_or(true, |expected| *byte == expected))
+        });
+
+        if let Some(offset) = found {
+            return Ok(Some(address + offset as u64));
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse_signature(signature: &str) -> Result<Vec<Option<u8>>> {
+    signature
+        .split_whitespace()
+        .map(|byte| match byte {
+            "??" | "?" => Ok(None),
+            _ => {
+                Ok(Some(u8::from_str_radix(byte, 16).with_context(|| {
+                    format!("invalid signature byte '{byte}'")
+                })?))
+            }
+        })
+        .collect()
+}
+
+fn parse_number<E: de::Error>(raw: &str) -> Result<u64, E> {
+    let digits = raw.trim_start_matches("0x").trim_start_matches("0X");
+    u64::from_str_radix(digits, 16).map_err(|_| E::custom(format!("invalid hex number '{raw}'")))
+}
+
+/// Numbers accepted either as JSON integers or `0x`-prefixed hex strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawNumber {
+    Integer(u64),
+    Hex(String),
+}
+
+impl RawNumber {
+    fn value<E: de::Error>(self) -> Result<u64, E> {
+        match self {
+            RawNumber::Integer(value) => Ok(value),
+            RawNumber::Hex(raw) => parse_number(&raw),
+        }
+    }
+}
+
+mod hex_number {
+    use super::RawNumber;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value:#010x}"))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        RawNumber::deserialize(deserializer)?.value()
+    }
+}
+
+mod hex_number_option {
+    use super::RawNumber;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Option<u64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::hex_number::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        Option::<RawNumber>::deserialize(deserializer)?
+            .map(RawNumber::value)
+            .transpose()
+    }
+}
+
+mod hex_numbers {
+    use super::RawNumber;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        values: &[u64],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let hex: Vec<String> = values.iter().map(|value| format!("{value:#x}")).collect();
+        serializer.collect_seq(hex)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u64>, D::Error> {
+        Vec::<RawNumber>::deserialize(deserializer)?
+            .into_iter()
+            .map(RawNumber::value)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATABASE: &str = r#"{
+        "BLUS30001": {
+            "name": "Example Game",
+            "versions": {
+                "*": [
+                    { "name": "No recoil", "address": "0x0012A4B0", "bytes": "60000000" }
+                ],
+                "01.00": [
+                    { "name": "Infinite ammo", "address": 1221808, "bytes": "00000063" }
+                ],
+                "01.02": [
+                    { "name": "Infinite ammo", "pointer": ["0x01C8F140", 32, "4C"], "bytes": "000003E7" },
+                    { "name": "Skip intro", "signature": "38 60 ?? 01", "offset": -2, "start": 65536, "end": "0x20000", "bytes": "00" }
+                ]
+            }
+        },
+        "NPUB30002": {
+            "versions": {
+                "01.00": [
+                    { "name": "Unlock all", "description": "Every level", "signature": "4E 80 00 20", "bytes": "01" }
+                ]
+            }
+        }
+    }"#;
+
+    fn names(entries: &[PatchEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_entries_of_every_target_kind() {
+        let database = PatchDatabase::from_json(DATABASE).unwrap();
+        let title = &database.titles["BLUS30001"];
+
+        assert_eq!(title.name, "Example Game");
+        assert_eq!(title.versions.len(), 3);
+
+        let patches = &title.versions["01.02"];
+        assert_eq!(
+            patches[0].target,
+            PatchTarget::Pointer {
+                pointer: vec![0x01C8_F140, 0x20, 0x4C]
+            }
+        );
+        assert_eq!(patches[0].bytes, vec![0x00, 0x00, 0x03, 0xE7]);
+        assert_eq!(
+            patches[1].target,
+            PatchTarget::Signature {
+                signature: "38 60 ?? 01".to_string(),
+                offset: -2,
+                start: Some(0x10000),
+                end: Some(0x20000),
+            }
+        );
+
+        let unlock = &database.titles["NPUB30002"].versions["01.00"][0];
+        assert_eq!(unlock.description, "Every level");
+        assert_eq!(
+            unlock.target,
+            PatchTarget::Signature {
+                signature: "4E 80 00 20".to_string(),
+                offset: 0,
+                start: None,
+                end: None,
+            }
+        );
+    }
+
+    #[test]
+    fn accepts_numbers_as_integers_or_hex_strings() {
+        let database = PatchDatabase::from_json(DATABASE).unwrap();
+        let versions = &database.titles["BLUS30001"].versions;
+
+        assert_eq!(
+            versions["*"][0].target,
+            PatchTarget::Address { address: 0x12A4B0 }
+        );
+        assert_eq!(
+            versions["01.00"][0].target,
+            PatchTarget::Address { address: 0x12A4B0 }
+        );
+
+        let invalid = r#"{ "BLUS30001": { "versions": { "*": [
+            { "name": "Bad", "address": "0xZZ", "bytes": "00" }
+        ] } } }"#;
+        assert!(PatchDatabase::from_json(invalid).is_err());
+    }
+
+    #[test]
+    fn selects_entries_by_version() {
+        let database = PatchDatabase::from_json(DATABASE).unwrap();
+
+        let entries = database.entries("BLUS30001", Some("01.02")).unwrap();
+        assert_eq!(
+            names(&entries),
+            vec!["No recoil", "Infinite ammo", "Skip intro"]
+        );
+        assert_eq!(
+            entries[1].target,
+            database.titles["BLUS30001"].versions["01.02"][0].target
+        );
+
+        let entries = database.entries("BLUS30001", Some("01.05")).unwrap();
+        assert_eq!(names(&entries), vec!["No recoil"]);
+
+        // Several versions are listed, so one must be given
+        assert!(database.entries("BLUS30001", None).is_err());
+
+        let entries = database.entries("NPUB30002", None).unwrap();
+        assert_eq!(names(&entries), vec!["Unlock all"]);
+
+        assert!(database.entries("BLES00001", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let database = PatchDatabase::from_json(DATABASE).unwrap();
+        let json = database.to_json().unwrap();
+
+        assert!(json.contains(r#""address": "0x0012a4b0""#));
+        assert_eq!(PatchDatabase::from_json(&json).unwrap(), database);
+    }
+
+    #[test]
+    fn parses_signatures_with_wildcards() {
+        assert_eq!(
+            parse_signature("38 60 ?? 01 ?").unwrap(),
+            vec![Some(0x38), Some(0x60), None, Some(0x01), None]
+        );
+        assert!(parse_signature("38 6G").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::snapshot::Snapshot;
+use crate::{MemoryRegion, CCAPI};
+use anyhow::Result;
+use serde::Serialize;
+use std::convert::TryInto;
+
+/// A run of consecutive bytes that differ between two snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedRange {
+    pub address: u64,
+    #[serde(serialize_with = "crate::hex::serialize")]
+    pub old: Vec<u8>,
+    #[serde(serialize_with = "crate::hex::serialize")]
+    pub new: Vec<u8>,
+    /// Aligned 32-bit words overlapping the change
+    pub words: Vec<WordChange>,
+}
+
+/// Typed interpretation of an aligned 32-bit word before and after a change
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WordChange {
+    pub address: u64,
+    pub old_u32: u32,
+    pub new_u32: u32,
+    pub old_f32: f32,
+    pub new_f32: f32,
+}
+
+/// All changes found within a single memory region
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionDiff {
+    pub region: MemoryRegion,
+    pub changes: Vec<ChangedRange>,
+}
+
+/// Result of comparing two snapshots
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    /// Regions present in both snapshots that contain at least one change
+    pub regions: Vec<RegionDiff>,
+    /// Regions of the first snapshot that are missing from the second one
+    pub missing: Vec<MemoryRegion>,
+}
+
+impl SnapshotDiff {
+    /// Returns the total amount of changed bytes
+    pub fn changed_bytes(&self) -> usize {
+        self.regions
+            .iter()
+            .flat_map(|region| &region.changes)
+            .map(|change| change.new.len())
+            .sum()
+    }
+
+    /// Returns whether any byte changed
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+/// Compares every region of `before` against the same region in `after`
+pub fn diff_snapshots(before: &Snapshot, after: &Snapshot) -> SnapshotDiff {
+    let mut diff = SnapshotDiff::default();
+
+    for saved in &before.regions {
+        let region = saved.region();
+
+        match after.read(region.address, region.size) {
+            Some(new) => {
+                let changes = diff_region(region.address, &saved.data, new);
+
+                if !changes.is_empty() {
+                    diff.regions.push(RegionDiff { region, changes });
+                }
+            }
+            None => diff.missing.push(region),
+        }
+    }
+
+    diff
+}
+
+/// Compares a snapshot against the current memory of a process
+///
+/// ### Arguments
+///
+/// * `ccapi` - The console to read from
+/// * `pid` - The process identifier to compare against
+/// * `snapshot` - The snapshot holding the earlier state
+pub fn diff_live(ccapi: &CCAPI, pid: u32, snapshot: &Snapshot) -> Result<SnapshotDiff> {
+    let live = Snapshot::capture(ccapi, pid, &snapshot.region_table())?;
+    Ok(diff_snapshots(snapshot, &live))
+}
+
+fn diff_region(address: u64, old: &[u8], new: &[u8]) -> Vec<ChangedRange> {
+    let mut changes = Vec::new();
+    let mut offset = 0;
+
+    while offset < old.len() {
+        if old[offset] == new[offset] {
+            offset += 1;
+            continue;
+        }
+
+        let start = offset;
+
+        while offset < old.len() && old[offset] != new[offset] {
+            offset += 1;
+        }
+
+        changes.push(ChangedRange {
+            address: address + start as u64,
+            old: old[start..offset].to_vec(),
+            new: new[start..offset].to_vec(),
+            words: word_changes(address, old, new, start, offset),
+        });
+    }
+
+    changes
+}
+
+fn word_changes(address: u64, old: &[u8], new: &[u8], start: usize, end: usize) -> Vec<WordChange> {
+    // Word alignment is relative to the absolute address, not the region start
+    let misalignment = (address % 4) as usize;
+    let first_word = (start + misalignment) / 4 * 4;
+
+    (first_word..end + misalignment)
+        .step_by(4)
+        .filter_map(|aligned| {
+            let word_start = aligned.checked_sub(misalignment)?;
+            let old_word: [u8; 4] = old.get(word_start..word_start + 4)?.try_into().ok()?;
+            let new_word: [u8; 4] = new.get(word_start..word_start + 4)?.try_into().ok()?;
+
+            Some(WordChange {
+                address: address + word_start as u64,
+                old_u32: u32::from_be_bytes(old_word),
+                new_u32: u32::from_be_bytes(new_word),
+                old_f32: f32::from_be_bytes(old_word),
+                new_f32: f32::from_be_bytes(new_word),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word_addresses(change: &ChangedRange) -> Vec<u64> {
+        change.words.iter().map(|word| word.address).collect()
+    }
+
+    #[test]
+    fn finds_no_changes_in_equal_memory() {
+        let memory = [0x3F, 0x80, 0x00, 0x00, 0x12, 0x34];
+        assert!(diff_region(0x10000, &memory, &memory).is_empty());
+    }
+
+    #[test]
+    fn groups_consecutive_changed_bytes() {
+        let old = [0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let new = [0x00, 0x00, 0x00, 0x02, 0xFF, 0x00, 0x00, 0x07];
+        let changes = diff_region(0x10000, &old, &new);
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].address, 0x10003);
+        assert_eq!(changes[0].old, vec![0x01, 0x00]);
+        assert_eq!(changes[0].new, vec![0x02, 0xFF]);
+        assert_eq!(word_addresses(&changes[0]), vec![0x10000, 0x10004]);
+        assert_eq!(changes[0].words[0].old_u32, 1);
+        assert_eq!(changes[0].words[0].new_u32, 2);
+
+        assert_eq!(changes[1].address, 0x10007);
+        assert_eq!(word_addresses(&changes[1]), vec![0x10004]);
+        assert_eq!(changes[1].words[0].new_u32, 0xFF00_0007);
+    }
+
+    #[test]
+    fn aligns_words_to_absolute_addresses() {
+        // The region starts halfway into a word, which is skipped as it is not fully saved
+        let old = [0x00, 0x00, 0x3F, 0x80, 0x00, 0x00];
+        let new = [0x01, 0x00, 0x40, 0x00, 0x00, 0x00];
+        let changes = diff_region(0x10002, &old, &new);
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].address, 0x10002);
+        assert!(changes[0].words.is_empty());
+
+        assert_eq!(changes[1].address, 0x10004);
+        assert_eq!(word_addresses(&changes[1]), vec![0x10004]);
+        assert_eq!(changes[1].words[0].old_f32, 1.0);
+        assert_eq!(changes[1].words[0].new_f32, 2.0);
+    }
+
+    #[test]
+    fn reports_changes_in_a_trailing_partial_word() {
+        let old = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let new = [0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let changes = diff_region(0x10000, &old, &new);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].address, 0x10005);
+        assert_eq!(changes[0].new, vec![0x01]);
+        assert!(changes[0].words.is_empty());
+    }
+}
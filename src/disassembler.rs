@@ -0,0 +1,1095 @@
+//! Disassembling PowerPC (Cell PPU) machine code, including common VMX (Altivec) and SPR
+//! instructions
+//!
+//! Words that cannot be decoded are shown as `.long <word>`, so the output can be fed back
+//! into [`crate::assembler`].
+
+use crate::assembler::{INSTRUCTION_SIZE, NOP};
+use crate::elf::SymbolMap;
+use crate::snapshot::chunks;
+use crate::{MemoryRegion, CCAPI};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::fmt;
+
+/// A single decoded instruction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Instruction {
+    /// Address the instruction was read from
+    pub address: u64,
+    /// Raw big-endian instruction word
+    pub word: u32,
+    pub mnemonic: String,
+    /// Comma separated operands, with branch targets resolved to absolute addresses
+    pub operands: String,
+    /// Destination of direct branches
+    pub target: Option<u64>,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}  {:08X}  ", self.address, self.word)?;
+
+        match self.operands.is_empty() {
+            true => write!(f, "{}", self.mnemonic),
+            false => write!(f, "{:<10}{}", self.mnemonic, self.operands),
+        }
+    }
+}
+
+/// Decodes a single instruction word located at the given address
+pub fn decode(word: u32, address: u64) -> Instruction {
+    let decoded = decode_word(word, address)
+        .unwrap_or_else(|| Decoded::new(".long", format!("{word:#010x}")));
+
+    Instruction {
+        address,
+        word,
+        mnemonic: decoded.mnemonic,
+        operands: decoded.operands,
+        target: decoded.target,
+    }
+}
+
+/// Disassembles big-endian machine code located at the given address
+///
+/// Trailing bytes that do not form a whole instruction are ignored.
+pub fn disassemble(bytes: &[u8], address: u64) -> Vec<Instruction> {
+    bytes
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            decode(word, address + index as u64 * 4)
+        })
+        .collect()
+}
+
+/// Reads and disassembles instructions from the memory of a process
+///
+/// ### Arguments
+///
+/// * `pid` - The process to read from
+/// * `address` - Address of the first instruction
+/// * `count` - Number of instructions to decode
+pub fn disassemble_process(
+    ccapi: &CCAPI,
+    pid: u32,
+    address: u64,
+    count: u32,
+) -> Result<Vec<Instruction>> {
+    let size = match (count as u64).checked_mul(INSTRUCTION_SIZE) {
+        Some(size) => size,
+        None => bail!("instruction count '{count}' is too large"),
+    };
+
+    let mut bytes = Vec::with_capacity(size as usize);
+
+    for (chunk_address, chunk_size) in chunks(&MemoryRegion::new(address, size)) {
+        bytes.extend(ccapi.read_process_memory(&pid, &chunk_address, &chunk_size)?);
+    }
+
+    Ok(disassemble(&bytes, address))
+}
+
+/// Appends the symbol containing each branch target to the operands, as `<symbol+0x10>`
+pub fn annotate(instructions: &mut [Instruction], symbols: &SymbolMap) {
+    for instruction in instructions {
+        if let Some(target) = instruction.target {
+            if symbols.lookup(target).is_some() {
+                let name = symbols.describe(target);
+                instruction.operands.push_str(&format!(" <{name}>"));
+            }
+        }
+    }
+}
+
+struct Decoded {
+    mnemonic: String,
+    operands: String,
+    target: Option<u64>,
+}
+
+impl Decoded {
+    fn new<M: Into<String>, O: Into<String>>(mnemonic: M, operands: O) -> Self {
+        Decoded {
+            mnemonic: mnemonic.into(),
+            operands: operands.into(),
+            target: None,
+        }
+    }
+
+    fn branch<M: Into<String>>(mnemonic: M, prefix: &str, target: u64) -> Self {
+        let operands = match prefix.is_empty() {
+            true => format!("{target:#x}"),
+            false => format!("{prefix}, {target:#x}"),
+        };
+
+        Decoded {
+            mnemonic: mnemonic.into(),
+            operands,
+            target: Some(target),
+        }
+    }
+}
+
+const CONDITIONS_TRUE: [&str; 4] = ["lt", "gt", "eq", "so"];
+const CONDITIONS_FALSE: [&str; 4] = ["ge", "le", "ne", "ns"];
+
+const LOAD_STORE: [&str; 24] = [
+    "lwz", "lwzu", "lbz", "lbzu", "stw", "stwu", "stb", "stbu", "lhz", "lhzu", "lha", "lhau",
+    "sth", "sthu", "lmw", "stmw", "lfs", "lfsu", "lfd", "lfdu", "stfs", "stfsu", "stfd", "stfdu",
+];
+
+/// Primary opcode 31 XO-form arithmetic, keyed by the 9-bit extended opcode.
+/// The flag marks instructions without an `rB` operand.
+const ARITHMETIC: &[(u32, &str, bool)] = &[
+    (8, "subfc", false),
+    (9, "mulhdu", false),
+    (10, "addc", false),
+    (11, "mulhwu", false),
+    (40, "subf", false),
+    (73, "mulhd", false),
+    (75, "mulhw", false),
+    (104, "neg", true),
+    (136, "subfe", false),
+    (138, "adde", false),
+    (200, "subfze", true),
+    (202, "addze", true),
+    (232, "subfme", true),
+    (233, "mulld", false),
+    (234, "addme", true),
+    (235, "mullw", false),
+    (266, "add", false),
+    (457, "divdu", false),
+    (459, "divwu", false),
+    (489, "divd", false),
+    (491, "divw", false),
+];
+
+/// Primary opcode 31 X-form logical and shift instructions (`rA, rS, rB`)
+const LOGICAL: &[(u32, &str)] = &[
+    (24, "slw"),
+    (27, "sld"),
+    (28, "and"),
+    (60, "andc"),
+    (124, "nor"),
+    (284, "eqv"),
+    (316, "xor"),
+    (412, "orc"),
+    (444, "or"),
+    (476, "nand"),
+    (536, "srw"),
+    (539, "srd"),
+    (792, "sraw"),
+    (794, "srad"),
+];
+
+/// Primary opcode 31 X-form instructions taking only `rA, rS`
+const UNARY: &[(u32, &str)] = &[
+    (26, "cntlzw"),
+    (58, "cntlzd"),
+    (922, "extsh"),
+    (954, "extsb"),
+    (986, "extsw"),
+];
+
+#[derive(Clone, Copy)]
+enum RegisterKind {
+    General,
+    Float,
+    Vector,
+}
+
+/// Primary opcode 31 indexed loads and stores (`xD, rA, rB`)
+const INDEXED: &[(u32, &str, RegisterKind)] = &[
+    (20, "lwarx", RegisterKind::General),
+    (21, "ldx", RegisterKind::General),
+    (23, "lwzx", RegisterKind::General),
+    (53, "ldux", RegisterKind::General),
+    (55, "lwzux", RegisterKind::General),
+    (84, "ldarx", RegisterKind::General),
+    (87, "lbzx", RegisterKind::General),
+    (119, "lbzux", RegisterKind::General),
+    (149, "stdx", RegisterKind::General),
+    (150, "stwcx.", RegisterKind::General),
+    (151, "stwx", RegisterKind::General),
+    (181, "stdux", RegisterKind::General),
+    (183, "stwux", RegisterKind::General),
+    (214, "stdcx.", RegisterKind::General),
+    (215, "stbx", RegisterKind::General),
+    (247, "stbux", RegisterKind::General),
+    (279, "lhzx", RegisterKind::General),
+    (311, "lhzux", RegisterKind::General),
+    (341, "lwax", RegisterKind::General),
+    (343, "lhax", RegisterKind::General),
+    (407, "sthx", RegisterKind::General),
+    (439, "sthux", RegisterKind::General),
+    (534, "lwbrx", RegisterKind::General),
+    (662, "stwbrx", RegisterKind::General),
+    (790, "lhbrx", RegisterKind::General),
+    (918, "sthbrx", RegisterKind::General),
+    (535, "lfsx", RegisterKind::Float),
+    (567, "lfsux", RegisterKind::Float),
+    (599, "lfdx", RegisterKind::Float),
+    (631, "lfdux", RegisterKind::Float),
+    (663, "stfsx", RegisterKind::Float),
+    (695, "stfsux", RegisterKind::Float),
+    (727, "stfdx", RegisterKind::Float),
+    (759, "stfdux", RegisterKind::Float),
+    (983, "stfiwx", RegisterKind::Float),
+    (6, "lvsl", RegisterKind::Vector),
+    (7, "lvebx", RegisterKind::Vector),
+    (38, "lvsr", RegisterKind::Vector),
+    (39, "lvehx", RegisterKind::Vector),
+    (71, "lvewx", RegisterKind::Vector),
+    (103, "lvx", RegisterKind::Vector),
+    (135, "stvebx", RegisterKind::Vector),
+    (167, "stvehx", RegisterKind::Vector),
+    (199, "stvewx", RegisterKind::Vector),
+    (231, "stvx", RegisterKind::Vector),
+    (359, "lvxl", RegisterKind::Vector),
+    (487, "stvxl", RegisterKind::Vector),
+];
+
+/// Primary opcode 31 cache management (`rA, rB`)
+const CACHE: &[(u32, &str)] = &[
+    (54, "dcbst"),
+    (86, "dcbf"),
+    (246, "dcbtst"),
+    (278, "dcbt"),
+    (470, "dcbi"),
+    (982, "icbi"),
+    (1014, "dcbz"),
+];
+
+/// Primary opcode 19 condition register logic (`crbD, crbA, crbB`)
+const CONDITION_LOGIC: &[(u32, &str)] = &[
+    (33, "crnor"),
+    (129, "crandc"),
+    (193, "crxor"),
+    (225, "crnand"),
+    (257, "crand"),
+    (289, "creqv"),
+    (417, "crorc"),
+    (449, "cror"),
+];
+
+/// Primary opcode 63 X-form floating point instructions taking `frD, frB`
+const FLOAT_UNARY: &[(u32, &str)] = &[
+    (12, "frsp"),
+    (14, "fctiw"),
+    (15, "fctiwz"),
+    (40, "fneg"),
+    (72, "fmr"),
+    (136, "fnabs"),
+    (264, "fabs"),
+    (814, "fctid"),
+    (815, "fctidz"),
+    (846, "fcfid"),
+];
+
+/// VMX VX-form instructions taking `vD, vA, vB`
+const VECTOR_BINARY: &[(u32, &str)] = &[
+    (0, "vaddubm"),
+    (2, "vmaxub"),
+    (4, "vrlb"),
+    (8, "vmuloub"),
+    (10, "vaddfp"),
+    (12, "vmrghb"),
+    (14, "vpkuhum"),
+    (64, "vadduhm"),
+    (66, "vmaxuh"),
+    (68, "vrlh"),
+    (72, "vmulouh"),
+    (74, "vsubfp"),
+    (76, "vmrghh"),
+    (78, "vpkuwum"),
+    (128, "vadduwm"),
+    (130, "vmaxuw"),
+    (132, "vrlw"),
+    (140, "vmrghw"),
+    (142, "vpkuhus"),
+    (206, "vpkuwus"),
+    (258, "vmaxsb"),
+    (260, "vslb"),
+    (264, "vmulosb"),
+    (268, "vmrglb"),
+    (322, "vmaxsh"),
+    (324, "vslh"),
+    (328, "vmulosh"),
+    (332, "vmrglh"),
+    (384, "vaddcuw"),
+    (386, "vmaxsw"),
+    (388, "vslw"),
+    (396, "vmrglw"),
+    (398, "vpkshss"),
+    (452, "vsl"),
+    (462, "vpkswss"),
+    (512, "vaddubs"),
+    (514, "vminub"),
+    (516, "vsrb"),
+    (520, "vmuleub"),
+    (576, "vadduhs"),
+    (578, "vminuh"),
+    (580, "vsrh"),
+    (584, "vmuleuh"),
+    (640, "vadduws"),
+    (642, "vminuw"),
+    (644, "vsrw"),
+    (708, "vsr"),
+    (768, "vaddsbs"),
+    (770, "vminsb"),
+    (772, "vsrab"),
+    (776, "vmulesb"),
+    (832, "vaddshs"),
+    (834, "vminsh"),
+    (836, "vsrah"),
+    (840, "vmulesh"),
+    (896, "vaddsws"),
+    (898, "vminsw"),
+    (900, "vsraw"),
+    (1024, "vsububm"),
+    (1028, "vand"),
+    (1034, "vmaxfp"),
+    (1036, "vslo"),
+    (1088, "vsubuhm"),
+    (1092, "vandc"),
+    (1098, "vminfp"),
+    (1100, "vsro"),
+    (1152, "vsubuwm"),
+    (1156, "vor"),
+    (1220, "vxor"),
+    (1284, "vnor"),
+    (1536, "vsububs"),
+    (1600, "vsubuhs"),
+    (1664, "vsubuws"),
+    (1792, "vsubsbs"),
+    (1856, "vsubshs"),
+    (1920, "vsubsws"),
+];
+
+/// VMX VX-form instructions taking `vD, vB`
+const VECTOR_UNARY: &[(u32, &str)] = &[
+    (266, "vrefp"),
+    (330, "vrsqrtefp"),
+    (394, "vexptefp"),
+    (458, "vlogefp"),
+    (522, "vrfin"),
+    (526, "vupkhsb"),
+    (586, "vrfiz"),
+    (590, "vupkhsh"),
+    (650, "vrfip"),
+    (654, "vupklsb"),
+    (714, "vrfim"),
+    (718, "vupklsh"),
+];
+
+/// VMX VX-form instructions taking `vD, vB, UIMM`
+const VECTOR_IMMEDIATE: &[(u32, &str)] = &[
+    (524, "vspltb"),
+    (588, "vsplth"),
+    (652, "vspltw"),
+    (778, "vcfux"),
+    (842, "vcfsx"),
+    (906, "vctuxs"),
+    (970, "vctsxs"),
+];
+
+/// VMX VX-form splat immediates taking `vD, SIMM`
+const VECTOR_SPLAT: &[(u32, &str)] = &[(780, "vspltisb"), (844, "vspltish"), (908, "vspltisw")];
+
+/// VMX VXR-form comparisons, keyed by the 10-bit extended opcode
+const VECTOR_COMPARE: &[(u32, &str)] = &[
+    (6, "vcmpequb"),
+    (70, "vcmpequh"),
+    (134, "vcmpequw"),
+    (198, "vcmpeqfp"),
+    (454, "vcmpgefp"),
+    (518, "vcmpgtub"),
+    (582, "vcmpgtuh"),
+    (646, "vcmpgtuw"),
+    (710, "vcmpgtfp"),
+    (774, "vcmpgtsb"),
+    (838, "vcmpgtsh"),
+    (902, "vcmpgtsw"),
+    (966, "vcmpbfp"),
+];
+
+/// VMX VA-form instructions taking `vD, vA, vB, vC`
+const VECTOR_TERNARY: &[(u32, &str)] = &[
+    (32, "vmhaddshs"),
+    (33, "vmhraddshs"),
+    (34, "vmladduhm"),
+    (42, "vsel"),
+    (43, "vperm"),
+];
+
+fn lookup<T: Copy>(table: &[(u32, T)], xo: u32) -> Option<T> {
+    table
+        .iter()
+        .find(|(key, _)| *key == xo)
+        .map(|(_, value)| *value)
+}
+
+fn bits(word: u32, shift: u32, width: u32) -> u32 {
+    (word >> shift) & ((1 << width) - 1)
+}
+
+fn signed_immediate(word: u32) -> i64 {
+    word as u16 as i16 as i64
+}
+
+fn signed_hex(value: i64) -> String {
+    match value < 0 {
+        true => format!("-{:#x}", -value),
+        false => format!("{value:#x}"),
+    }
+}
+
+fn r(number: u32) -> String {
+    format!("r{number}")
+}
+
+fn f(number: u32) -> String {
+    format!("f{number}")
+}
+
+fn v(number: u32) -> String {
+    format!("v{number}")
+}
+
+fn register(kind: RegisterKind, number: u32) -> String {
+    match kind {
+        RegisterKind::General => r(number),
+        RegisterKind::Float => f(number),
+        RegisterKind::Vector => v(number),
+    }
+}
+
+fn record(word: u32) -> &'static str {
+    match word & 1 {
+        1 => ".",
+        _ => "",
+    }
+}
+
+fn spr_name(spr: u32) -> Option<&'static str> {
+    match spr {
+        1 => Some("xer"),
+        8 => Some("lr"),
+        9 => Some("ctr"),
+        256 => Some("vrsave"),
+        268 => Some("tbl"),
+        269 => Some("tbu"),
+        _ => None,
+    }
+}
+
+/// Returns the condition suffix and condition register operand of a conditional branch,
+/// or `None` when the BO field has no simplified form
+fn condition(bo: u32, bi: u32) -> Option<(String, String)> {
+    let field = match bi / 4 {
+        0 => String::new(),
+        cr => format!("cr{cr}"),
+    };
+
+    match bo & 0x14 {
+        0x14 => Some((String::new(), String::new())),
+        0x04 if bo & 0x08 != 0 => Some((CONDITIONS_TRUE[(bi % 4) as usize].to_string(), field)),
+        0x04 => Some((CONDITIONS_FALSE[(bi % 4) as usize].to_string(), field)),
+        0x10 if bo & 0x02 != 0 => Some(("dz".to_string(), String::new())),
+        0x10 => Some(("dnz".to_string(), String::new())),
+        _ => None,
+    }
+}
+
+fn decode_word(word: u32, address: u64) -> Option<Decoded> {
+    let primary = word >> 26;
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let uimm = word & 0xFFFF;
+    let simm = signed_immediate(word);
+
+    let decoded = match primary {
+        2 => Decoded::new("tdi", format!("{d}, {}, {simm}", r(a))),
+        3 => Decoded::new("twi", format!("{d}, {}, {simm}", r(a))),
+        4 => return decode_vector(word),
+        7 => Decoded::new("mulli", format!("{}, {}, {simm}", r(d), r(a))),
+        8 => Decoded::new("subfic", format!("{}, {}, {simm}", r(d), r(a))),
+        10 | 11 => {
+            let field = bits(word, 23, 3);
+            let width = match bits(word, 21, 1) {
+                1 => "d",
+                _ => "w",
+            };
+            let (mnemonic, immediate) = match primary {
+                10 => (format!("cmpl{width}i"), format!("{uimm:#x}")),
+                _ => (format!("cmp{width}i"), simm.to_string()),
+            };
+
+            match field {
+                0 => Decoded::new(mnemonic, format!("{}, {immediate}", r(a))),
+                _ => Decoded::new(mnemonic, format!("cr{field}, {}, {immediate}", r(a))),
+            }
+        }
+        12 => Decoded::new("addic", format!("{}, {}, {simm}", r(d), r(a))),
+        13 => Decoded::new("addic.", format!("{}, {}, {simm}", r(d), r(a))),
+        14 if a == 0 => Decoded::new("li", format!("{}, {simm}", r(d))),
+        14 => Decoded::new("addi", format!("{}, {}, {simm}", r(d), r(a))),
+        15 if a == 0 => Decoded::new("lis", format!("{}, {uimm:#x}", r(d))),
+        15 => Decoded::new("addis", format!("{}, {}, {}", r(d), r(a), signed_hex(simm))),
+        16 => {
+            let offset = signed_immediate(word & 0xFFFC);
+            let absolute = word & 2 != 0;
+            let target = match absolute {
+                true => offset as u64,
+                false => address.wrapping_add(offset as u64),
+            };
+            let suffix = format!(
+                "{}{}",
+                if word & 1 != 0 { "l" } else { "" },
+                if absolute { "a" } else { "" }
+            );
+
+            match condition(d, a) {
+                Some((cond, field)) => Decoded::branch(format!("b{cond}{suffix}"), &field, target),
+                None => Decoded::branch(format!("bc{suffix}"), &format!("{d}, {a}"), target),
+            }
+        }
+        17 if word & 2 != 0 => Decoded::new("sc", ""),
+        18 => {
+            let offset = ((word & 0x03FF_FFFC) << 6) as i32 >> 6;
+            let absolute = word & 2 != 0;
+            let target = match absolute {
+                true => offset as i64 as u64,
+                false => address.wrapping_add(offset as i64 as u64),
+            };
+            let mnemonic = format!(
+                "b{}{}",
+                if word & 1 != 0 { "l" } else { "" },
+                if absolute { "a" } else { "" }
+            );
+
+            Decoded::branch(mnemonic, "", target)
+        }
+        19 => return decode_19(word),
+        20 | 21 | 23 => return Some(decode_rotate(word)),
+        24 if word == NOP => Decoded::new("nop", ""),
+        24..=29 => {
+            let mnemonic =
+                ["ori", "oris", "xori", "xoris", "andi.", "andis."][(primary - 24) as usize];
+            Decoded::new(mnemonic, format!("{}, {}, {uimm:#x}", r(a), r(d)))
+        }
+        30 => return decode_rotate_doubleword(word),
+        31 => return decode_31(word),
+        32..=55 => {
+            let mnemonic = LOAD_STORE[(primary - 32) as usize];
+            let target = match primary >= 48 {
+                true => f(d),
+                false => r(d),
+            };
+
+            Decoded::new(
+                mnemonic,
+                format!("{target}, {}({})", signed_hex(simm), r(a)),
+            )
+        }
+        58 | 62 => {
+            let mnemonic = match (primary, word & 3) {
+                (58, 0) => "ld",
+                (58, 1) => "ldu",
+                (58, 2) => "lwa",
+                (62, 0) => "std",
+                (62, 1) => "stdu",
+                _ => return None,
+            };
+            let displacement = signed_immediate(word & 0xFFFC);
+
+            Decoded::new(
+                mnemonic,
+                format!("{}, {}({})", r(d), signed_hex(displacement), r(a)),
+            )
+        }
+        59 | 63 => return decode_float(word),
+        _ => return None,
+    };
+
+    Some(decoded)
+}
+
+/// Branches to the link or count register and condition register logic
+fn decode_19(word: u32) -> Option<Decoded> {
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let b = bits(word, 11, 5);
+    let xo = bits(word, 1, 10);
+    let link = if word & 1 != 0 { "l" } else { "" };
+
+    let decoded = match xo {
+        0 => Decoded::new(
+            "mcrf",
+            format!("cr{}, cr{}", bits(word, 23, 3), bits(word, 18, 3)),
+        ),
+        16 | 528 => {
+            let register = if xo == 16 { "lr" } else { "ctr" };
+
+            match condition(d, a) {
+                Some((cond, field)) => Decoded::new(format!("b{cond}{register}{link}"), field),
+                None => Decoded::new(format!("bc{register}{link}"), format!("{d}, {a}")),
+            }
+        }
+        150 => Decoded::new("isync", ""),
+        _ => {
+            let mnemonic = lookup(CONDITION_LOGIC, xo)?;
+            Decoded::new(mnemonic, format!("{d}, {a}, {b}"))
+        }
+    };
+
+    Some(decoded)
+}
+
+/// 32-bit rotate and mask instructions, with the common shift forms simplified
+fn decode_rotate(word: u32) -> Decoded {
+    let s = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let sh = bits(word, 11, 5);
+    let mb = bits(word, 6, 5);
+    let me = bits(word, 1, 5);
+    let rc = record(word);
+    let registers = format!("{}, {}", r(a), r(s));
+
+    match word >> 26 {
+        20 => Decoded::new(
+            format!("rlwimi{rc}"),
+            format!("{registers}, {sh}, {mb}, {me}"),
+        ),
+        21 if mb == 0 && me == 31 - sh => {
+            Decoded::new(format!("slwi{rc}"), format!("{registers}, {sh}"))
+        }
+        21 if sh != 0 && sh + mb == 32 && me == 31 => {
+            Decoded::new(format!("srwi{rc}"), format!("{registers}, {mb}"))
+        }
+        21 if sh == 0 && me == 31 => {
+            Decoded::new(format!("clrlwi{rc}"), format!("{registers}, {mb}"))
+        }
+        21 => Decoded::new(
+            format!("rlwinm{rc}"),
+            format!("{registers}, {sh}, {mb}, {me}"),
+        ),
+        _ if mb == 0 && me == 31 => {
+            Decoded::new(format!("rotlw{rc}"), format!("{registers}, {}", r(sh)))
+        }
+        _ => Decoded::new(
+            format!("rlwnm{rc}"),
+            format!("{registers}, {}, {mb}, {me}", r(sh)),
+        ),
+    }
+}
+
+/// 64-bit rotate and mask instructions, with the common shift forms simplified
+fn decode_rotate_doubleword(word: u32) -> Option<Decoded> {
+    let s = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let sh = bits(word, 11, 5) | (word & 2) << 4;
+    let mb = bits(word, 6, 5) | (word & 0x20);
+    let rc = record(word);
+    let registers = format!("{}, {}", r(a), r(s));
+
+    let decoded = match bits(word, 2, 3) {
+        0 if sh == 0 => Decoded::new(format!("clrldi{rc}"), format!("{registers}, {mb}")),
+        0 if mb == 0 => Decoded::new(format!("rotldi{rc}"), format!("{registers}, {sh}")),
+        0 if sh + mb == 64 => Decoded::new(format!("srdi{rc}"), format!("{registers}, {mb}")),
+        0 => Decoded::new(format!("rldicl{rc}"), format!("{registers}, {sh}, {mb}")),
+        1 if mb == 63 - sh => Decoded::new(format!("sldi{rc}"), format!("{registers}, {sh}")),
+        1 => Decoded::new(format!("rldicr{rc}"), format!("{registers}, {sh}, {mb}")),
+        2 => Decoded::new(format!("rldic{rc}"), format!("{registers}, {sh}, {mb}")),
+        3 => Decoded::new(format!("rldimi{rc}"), format!("{registers}, {sh}, {mb}")),
+        _ => {
+            let mnemonic = match bits(word, 1, 4) {
+                8 => "rldcl",
+                9 => "rldcr",
+                _ => return None,
+            };
+            let rb = r(bits(word, 11, 5));
+
+            Decoded::new(
+                format!("{mnemonic}{rc}"),
+                format!("{registers}, {rb}, {mb}"),
+            )
+        }
+    };
+
+    Some(decoded)
+}
+
+/// Integer arithmetic, logic, indexed memory access, SPR moves and synchronisation
+fn decode_31(word: u32) -> Option<Decoded> {
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let b = bits(word, 11, 5);
+    let xo = bits(word, 1, 10);
+    let rc = record(word);
+
+    if bits(word, 2, 9) == 413 {
+        let sh = b | (word & 2) << 4;
+        return Some(Decoded::new(
+            format!("sradi{rc}"),
+            format!("{}, {}, {sh}", r(a), r(d)),
+        ));
+    }
+
+    if let Some((_, mnemonic, unary)) = ARITHMETIC
+        .iter()
+        .find(|(key, _, _)| *key == bits(word, 1, 9))
+    {
+        let overflow = if bits(word, 10, 1) != 0 { "o" } else { "" };
+        let operands = match unary {
+            true => format!("{}, {}", r(d), r(a)),
+            false => format!("{}, {}, {}", r(d), r(a), r(b)),
+        };
+
+        return Some(Decoded::new(format!("{mnemonic}{overflow}{rc}"), operands));
+    }
+
+    if let Some(mnemonic) = lookup(LOGICAL, xo) {
+        let decoded = match (mnemonic, d == b) {
+            ("or", true) => Decoded::new(format!("mr{rc}"), format!("{}, {}", r(a), r(d))),
+            ("nor", true) => Decoded::new(format!("not{rc}"), format!("{}, {}", r(a), r(d))),
+            _ => Decoded::new(
+                format!("{mnemonic}{rc}"),
+                format!("{}, {}, {}", r(a), r(d), r(b)),
+            ),
+        };
+
+        return Some(decoded);
+    }
+
+    if let Some(mnemonic) = lookup(UNARY, xo) {
+        return Some(Decoded::new(
+            format!("{mnemonic}{rc}"),
+            format!("{}, {}", r(a), r(d)),
+        ));
+    }
+
+    if let Some((_, mnemonic, kind)) = INDEXED.iter().find(|(key, _, _)| *key == xo) {
+        return Some(Decoded::new(
+            *mnemonic,
+            format!("{}, {}, {}", register(*kind, d), r(a), r(b)),
+        ));
+    }
+
+    if let Some(mnemonic) = lookup(CACHE, xo) {
+        return Some(Decoded::new(mnemonic, format!("{}, {}", r(a), r(b))));
+    }
+
+    let spr = a | b << 5;
+
+    let decoded = match xo {
+        0 | 32 => {
+            let field = bits(word, 23, 3);
+            let mnemonic = format!(
+                "cmp{}{}",
+                if xo == 32 { "l" } else { "" },
+                if bits(word, 21, 1) != 0 { "d" } else { "w" }
+            );
+
+            match field {
+                0 => Decoded::new(mnemonic, format!("{}, {}", r(a), r(b))),
+                _ => Decoded::new(mnemonic, format!("cr{field}, {}, {}", r(a), r(b))),
+            }
+        }
+        4 if d == 31 && a == 0 && b == 0 => Decoded::new("trap", ""),
+        4 => Decoded::new("tw", format!("{d}, {}, {}", r(a), r(b))),
+        68 => Decoded::new("td", format!("{d}, {}, {}", r(a), r(b))),
+        19 => Decoded::new("mfcr", r(d)),
+        144 => match bits(word, 12, 8) {
+            0xFF => Decoded::new("mtcr", r(d)),
+            mask => Decoded::new("mtcrf", format!("{mask:#x}, {}", r(d))),
+        },
+        339 => match spr_name(spr) {
+            Some(name @ "xer") | Some(name @ "lr") | Some(name @ "ctr") => {
+                Decoded::new(format!("mf{name}"), r(d))
+            }
+            Some(name) => Decoded::new("mfspr", format!("{}, {name}", r(d))),
+            None => Decoded::new("mfspr", format!("{}, {spr}", r(d))),
+        },
+        467 => match spr_name(spr) {
+            Some(name @ "xer") | Some(name @ "lr") | Some(name @ "ctr") => {
+                Decoded::new(format!("mt{name}"), r(d))
+            }
+            Some(name) => Decoded::new("mtspr", format!("{name}, {}", r(d))),
+            None => Decoded::new("mtspr", format!("{spr}, {}", r(d))),
+        },
+        371 => match spr {
+            269 => Decoded::new("mftbu", r(d)),
+            _ => Decoded::new("mftb", r(d)),
+        },
+        598 => match bits(word, 21, 2) {
+            1 => Decoded::new("lwsync", ""),
+            _ => Decoded::new("sync", ""),
+        },
+        854 => Decoded::new("eieio", ""),
+        824 => Decoded::new(format!("srawi{rc}"), format!("{}, {}, {b}", r(a), r(d))),
+        _ => return None,
+    };
+
+    Some(decoded)
+}
+
+/// Single (59) and double (63) precision floating point
+fn decode_float(word: u32) -> Option<Decoded> {
+    let single = word >> 26 == 59;
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let b = bits(word, 11, 5);
+    let c = bits(word, 6, 5);
+    let rc = record(word);
+    let precision = if single { "s" } else { "" };
+
+    let decoded = match (bits(word, 1, 5), single) {
+        (18, _) => Decoded::new(
+            format!("fdiv{precision}{rc}"),
+            format!("{}, {}, {}", f(d), f(a), f(b)),
+        ),
+        (20, _) => Decoded::new(
+            format!("fsub{precision}{rc}"),
+            format!("{}, {}, {}", f(d), f(a), f(b)),
+        ),
+        (21, _) => Decoded::new(
+            format!("fadd{precision}{rc}"),
+            format!("{}, {}, {}", f(d), f(a), f(b)),
+        ),
+        (22, _) => Decoded::new(
+            format!("fsqrt{precision}{rc}"),
+            format!("{}, {}", f(d), f(b)),
+        ),
+        (23, false) => Decoded::new(
+            format!("fsel{rc}"),
+            format!("{}, {}, {}, {}", f(d), f(a), f(c), f(b)),
+        ),
+        (24, true) => Decoded::new(format!("fres{rc}"), format!("{}, {}", f(d), f(b))),
+        (25, _) => Decoded::new(
+            format!("fmul{precision}{rc}"),
+            format!("{}, {}, {}", f(d), f(a), f(c)),
+        ),
+        (26, false) => Decoded::new(format!("frsqrte{rc}"), format!("{}, {}", f(d), f(b))),
+        (xo @ 28..=31, _) => {
+            let mnemonic = ["fmsub", "fmadd", "fnmsub", "fnmadd"][(xo - 28) as usize];
+            Decoded::new(
+                format!("{mnemonic}{precision}{rc}"),
+                format!("{}, {}, {}, {}", f(d), f(a), f(c), f(b)),
+            )
+        }
+        (_, true) => return None,
+        (_, false) => {
+            let xo = bits(word, 1, 10);
+
+            match xo {
+                0 | 32 => Decoded::new(
+                    if xo == 0 { "fcmpu" } else { "fcmpo" },
+                    format!("cr{}, {}, {}", bits(word, 23, 3), f(a), f(b)),
+                ),
+                583 => Decoded::new(format!("mffs{rc}"), f(d)),
+                711 => Decoded::new(
+                    format!("mtfsf{rc}"),
+                    format!("{:#x}, {}", bits(word, 17, 8), f(b)),
+                ),
+                _ => {
+                    let mnemonic = lookup(FLOAT_UNARY, xo)?;
+                    Decoded::new(format!("{mnemonic}{rc}"), format!("{}, {}", f(d), f(b)))
+                }
+            }
+        }
+    };
+
+    Some(decoded)
+}
+
+/// VMX (Altivec) instructions
+fn decode_vector(word: u32) -> Option<Decoded> {
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let b = bits(word, 11, 5);
+    let c = bits(word, 6, 5);
+
+    let decoded = match bits(word, 0, 6) {
+        44 => Decoded::new(
+            "vsldoi",
+            format!("{}, {}, {}, {}", v(d), v(a), v(b), bits(word, 6, 4)),
+        ),
+        46 => Decoded::new("vmaddfp", format!("{}, {}, {}, {}", v(d), v(a), v(c), v(b))),
+        47 => Decoded::new(
+            "vnmsubfp",
+            format!("{}, {}, {}, {}", v(d), v(a), v(c), v(b)),
+        ),
+        xo @ 32..=47 => {
+            let mnemonic = lookup(VECTOR_TERNARY, xo)?;
+            Decoded::new(mnemonic, format!("{}, {}, {}, {}", v(d), v(a), v(b), v(c)))
+        }
+        _ => return decode_vector_vx(word),
+    };
+
+    Some(decoded)
+}
+
+fn decode_vector_vx(word: u32) -> Option<Decoded> {
+    let d = bits(word, 21, 5);
+    let a = bits(word, 16, 5);
+    let b = bits(word, 11, 5);
+    let xo = bits(word, 0, 11);
+
+    if let Some(mnemonic) = lookup(VECTOR_COMPARE, bits(word, 0, 10)) {
+        let rc = if bits(word, 10, 1) != 0 { "." } else { "" };
+        return Some(Decoded::new(
+            format!("{mnemonic}{rc}"),
+            format!("{}, {}, {}", v(d), v(a), v(b)),
+        ));
+    }
+
+    let decoded = match xo {
+        1540 => Decoded::new("mfvscr", v(d)),
+        1604 => Decoded::new("mtvscr", v(b)),
+        _ => {
+            if let Some(mnemonic) = lookup(VECTOR_BINARY, xo) {
+                Decoded::new(mnemonic, format!("{}, {}, {}", v(d), v(a), v(b)))
+            } else if let Some(mnemonic) = lookup(VECTOR_UNARY, xo) {
+                Decoded::new(mnemonic, format!("{}, {}", v(d), v(b)))
+            } else if let Some(mnemonic) = lookup(VECTOR_IMMEDIATE, xo) {
+                Decoded::new(mnemonic, format!("{}, {}, {a}", v(d), v(b)))
+            } else {
+                let mnemonic = lookup(VECTOR_SPLAT, xo)?;
+                let immediate = ((a << 27) as i32) >> 27;
+                Decoded::new(mnemonic, format!("{}, {immediate}", v(d)))
+            }
+        }
+    };
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler;
+
+    const BASE: u64 = 0x10000;
+
+    /// One line per mnemonic supported by the assembler
+    const ROUND_TRIP_SOURCE: &str = "
+        li r3, 10
+        lis r4, 0x1234
+        addi r3, r1, 8
+        addis r3, r3, -2
+        ori r4, r4, 0x5678
+        oris r4, r4, 1
+        mr r3, r4
+        add r3, r4, r5
+        subf r3, r4, r5
+        mflr r0
+        mtlr r0
+        mtctr r12
+        lbz r3, 1(r4)
+        lhz r3, 2(r4)
+        lwz r3, 8(r1)
+        lwzu r3, 8(r1)
+        ld r3, 16(r1)
+        stb r3, 1(r4)
+        sth r3, 2(r4)
+        stw r0, -4(r1)
+        stwu r1, -128(r1)
+        std r0, 16(r1)
+        lfs f1, 4(r3)
+        lfd f2, 8(r3)
+        stfs f1, 4(r3)
+        stfd f2, 8(r3)
+        cmpwi r3, 0
+        cmpwi cr7, r3, 5
+        cmplwi r3, 10
+        cmpw r3, r4
+        cmplw cr1, r3, r4
+        b 0x10100
+        bl 0x10000
+        ba 0x100
+        beq 0x10200
+        bne cr7, 0x10000
+        blt 0x10200
+        bge 0x10200
+        bgt 0x10200
+        ble 0x10200
+        bdnz 0x10000
+        blr
+        blrl
+        bctr
+        bctrl
+        nop
+    ";
+
+    fn text(word: u32) -> String {
+        let instruction = decode(word, BASE);
+
+        match instruction.operands.is_empty() {
+            true => instruction.mnemonic,
+            false => format!("{} {}", instruction.mnemonic, instruction.operands),
+        }
+    }
+
+    #[test]
+    fn round_trips_assembled_instructions() {
+        let bytes = assembler::assemble(ROUND_TRIP_SOURCE, BASE).unwrap();
+        let instructions = disassemble(&bytes, BASE);
+
+        assert_eq!(instructions.len(), 46);
+
+        for instruction in instructions {
+            let source = format!("{} {}", instruction.mnemonic, instruction.operands);
+            let reassembled = assembler::assemble_words(&source, instruction.address).unwrap();
+
+            assert_ne!(instruction.mnemonic, ".long", "{instruction}");
+            assert_eq!(reassembled, vec![instruction.word], "{instruction}");
+        }
+    }
+
+    #[test]
+    fn decodes_vmx_instructions() {
+        assert_eq!(text(0x1061_1484), "vor v3, v1, v2");
+        assert_eq!(text(0x1000_04C4), "vxor v0, v0, v0");
+        assert_eq!(text(0x1043_2180), "vaddcuw v2, v3, v4");
+        assert_eq!(text(0x1060_1C4A), "vminfp v3, v0, v3");
+        assert_eq!(text(0x1022_190A), "vrefp v1, v3");
+        assert_eq!(text(0x1022_0C06), "vcmpequb. v1, v2, v1");
+        assert_eq!(text(0x7C00_18CE), "lvx v0, r0, r3");
+        assert_eq!(text(0x7C00_19CE), "stvx v0, r0, r3");
+    }
+
+    #[test]
+    fn decodes_special_purpose_registers() {
+        assert_eq!(text(0x7C08_02A6), "mflr r0");
+        assert_eq!(text(0x7C08_03A6), "mtlr r0");
+        assert_eq!(text(0x7D89_03A6), "mtctr r12");
+        assert_eq!(text(0x7C69_02A6), "mfctr r3");
+        assert_eq!(text(0x7C6C_42E6), "mftb r3");
+        assert_eq!(text(0x7C6D_42E6), "mftbu r3");
+        assert_eq!(text(0x7C7A_02A6), "mfspr r3, 26");
+        assert_eq!(text(0x7C9B_03A6), "mtspr 27, r4");
+    }
+
+    #[test]
+    fn decodes_conditional_branch_forms() {
+        assert_eq!(text(0x4182_0010), "beq 0x10010");
+        assert_eq!(text(0x4082_0010), "bne 0x10010");
+        assert_eq!(text(0x4180_0010), "blt 0x10010");
+        assert_eq!(text(0x409E_FFF0), "bne cr7, 0xfff0");
+        assert_eq!(text(0x4200_0010), "bdnz 0x10010");
+        assert_eq!(text(0x4D82_0020), "beqlr");
+        assert_eq!(text(0x4C80_0020), "bgelr");
+
+        assert_eq!(decode(0x4182_0010, BASE).target, Some(0x10010));
+        assert_eq!(decode(0x4D82_0020, BASE).target, None);
+    }
+
+    #[test]
+    fn shows_unknown_words_as_data() {
+        assert_eq!(text(0x0000_0000), ".long 0x00000000");
+    }
+}
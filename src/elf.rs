@@ -0,0 +1,609 @@
+//! Loading symbols from decrypted PPU executables (`EBOOT.ELF`) and modules (`.prx`)
+//!
+//! Besides the regular symbol table, which release builds usually lack, names are taken
+//! from the import and export stubs of the PS3 module format. Those are only identified by
+//! NID, so they are named `<library>::<NID>`.
+
+use anyhow::{bail, ensure, Context, Result};
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const ELF_MAGIC: &[u8] = b"\x7FELF";
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_BIG_ENDIAN: u8 = 2;
+const MACHINE_PPC64: u16 = 0x15;
+
+const PROGRAM_HEADER_SIZE: u64 = 0x38;
+const SECTION_HEADER_SIZE: u64 = 0x40;
+const SYMBOL_SIZE: u64 = 0x18;
+
+const SEGMENT_LOAD: u32 = 1;
+const SEGMENT_PROCESS_PARAM: u32 = 0x6000_0002;
+const SECTION_SYMBOL_TABLE: u32 = 2;
+const SECTION_DYNAMIC_SYMBOL_TABLE: u32 = 11;
+const SYMBOL_OBJECT: u8 = 1;
+const SYMBOL_FUNCTION: u8 = 2;
+
+const FILE_TYPE_PRX: u16 = 0xFFA4;
+const PRX_INFO_MAGIC: u32 = 0x1B43_4CEC;
+const IMPORT_SIZE: u64 = 0x2C;
+const EXPORT_SIZE: u64 = 0x1C;
+
+/// A loadable segment of an ELF file
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub kind: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub address: u64,
+    pub physical_address: u64,
+    pub file_size: u64,
+    pub memory_size: u64,
+}
+
+impl Segment {
+    /// Returns true if the segment is mapped executable
+    pub fn is_executable(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.file_size
+    }
+}
+
+/// A function descriptor, which PPU function pointers point to instead of code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Descriptor {
+    /// Address of the first instruction
+    pub function: u64,
+    /// Table of contents (`r2`) the function expects
+    pub toc: u64,
+}
+
+/// Where a symbol was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Object,
+    /// A library function called through an import stub
+    Import,
+    /// A function exported to other modules
+    Export,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    /// Size in bytes, or 0 if unknown
+    pub size: u64,
+    pub kind: SymbolKind,
+}
+
+/// A parsed PPU ELF64 file
+#[derive(Debug, Clone)]
+pub struct Elf {
+    /// `ET_EXEC` (2) for executables, `0xFFA4` for relocatable modules
+    pub file_type: u16,
+    /// Address of the descriptor of the entry point
+    pub entry: u64,
+    pub segments: Vec<Segment>,
+    data: Vec<u8>,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    name: String,
+    kind: u32,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+}
+
+/// Import or export table bounds
+struct ModuleTables {
+    exports: (u64, u64),
+    imports: (u64, u64),
+}
+
+impl Elf {
+    /// Parses an ELF file already in memory
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        ensure!(
+            data.len() >= 0x40 && data.starts_with(ELF_MAGIC),
+            "not an ELF file"
+        );
+        ensure!(
+            data[4] == ELF_CLASS_64 && data[5] == ELF_DATA_BIG_ENDIAN,
+            "not a big-endian ELF64 file, is it still encrypted?"
+        );
+
+        let machine = read_u16(&data, 0x12)?;
+        ensure!(
+            machine == MACHINE_PPC64,
+            "unsupported machine type '{machine:#x}'"
+        );
+
+        let file_type = read_u16(&data, 0x10)?;
+        let entry = read_u64(&data, 0x18)?;
+        let program_offset = read_u64(&data, 0x20)?;
+        let section_offset = read_u64(&data, 0x28)?;
+        let program_count = read_u16(&data, 0x38)? as u64;
+        let section_count = read_u16(&data, 0x3C)? as u64;
+        let names_index = read_u16(&data, 0x3E)? as u64;
+
+        let segments = (0..program_count)
+            .map(|index| {
+                let header = entry_offset(&data, program_offset, index, PROGRAM_HEADER_SIZE)?;
+
+                Ok(Segment {
+                    kind: read_u32(&data, header)?,
+                    flags: read_u32(&data, header + 0x04)?,
+                    offset: read_u64(&data, header + 0x08)?,
+                    address: read_u64(&data, header + 0x10)?,
+                    physical_address: read_u64(&data, header + 0x18)?,
+                    file_size: read_u64(&data, header + 0x20)?,
+                    memory_size: read_u64(&data, header + 0x28)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let names = match section_count {
+            0 => None,
+            _ => {
+                let header = entry_offset(&data, section_offset, names_index, SECTION_HEADER_SIZE)?;
+                Some(read_u64(&data, header + 0x18)?)
+            }
+        };
+
+        let sections = (0..section_count)
+            .map(|index| {
+                let header = entry_offset(&data, section_offset, index, SECTION_HEADER_SIZE)?;
+                let name = match names {
+                    Some(names) => {
+                        read_string(&data, add_offset(names, read_u32(&data, header)? as u64)?)
+                    }
+                    None => String::new(),
+                };
+
+                Ok(Section {
+                    name,
+                    kind: read_u32(&data, header + 0x04)?,
+                    address: read_u64(&data, header + 0x10)?,
+                    offset: read_u64(&data, header + 0x18)?,
+                    size: read_u64(&data, header + 0x20)?,
+                    link: read_u32(&data, header + 0x28)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Elf {
+            file_type,
+            entry,
+            segments,
+            data,
+            sections,
+        })
+    }
+
+    /// Reads and parses an ELF file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            fs::read(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+
+        Elf::parse(data).with_context(|| format!("Failed to parse '{}'", path.display()))
+    }
+
+    /// Returns true for relocatable PRX modules, whose addresses are relative to a load base
+    pub fn is_prx(&self) -> bool {
+        self.file_type == FILE_TYPE_PRX
+    }
+
+    /// Returns the bytes at a virtual address, as stored in the file
+    pub fn read(&self, address: u64, size: usize) -> Option<&[u8]> {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.kind == SEGMENT_LOAD && segment.contains(address))?;
+
+        let relative = address - segment.address;
+
+        if relative.checked_add(size as u64)? > segment.file_size {
+            return None;
+        }
+
+        let start = usize::try_from(segment.offset.checked_add(relative)?).ok()?;
+        let end = start.checked_add(size)?;
+
+        self.data.get(start..end)
+    }
+
+    fn read_u32_at(&self, address: u64) -> Result<u32> {
+        match self.read(address, 4) {
+            Some(bytes) => Ok(u32::from_be_bytes(bytes.try_into()?)),
+            None => bail!("address '{address:#x}' is not in any segment"),
+        }
+    }
+
+    /// Reads the function descriptor at an address
+    pub fn descriptor(&self, address: u64) -> Result<Descriptor> {
+        Ok(Descriptor {
+            function: self.read_u32_at(address)? as u64,
+            toc: self.read_u32_at(add_offset(address, 4)?)? as u64,
+        })
+    }
+
+    /// Returns the descriptor of the entry point
+    pub fn entry_descriptor(&self) -> Result<Descriptor> {
+        self.descriptor(self.entry)
+    }
+
+    /// Returns the table of contents address, taken from the entry point or module info
+    pub fn toc(&self) -> Result<u64> {
+        match self.is_prx() {
+            true => Ok(self.read_u32_at(add_offset(self.module_info()?, 0x20)?)? as u64),
+            false => Ok(self.entry_descriptor()?.toc),
+        }
+    }
+
+    /// Address of the `.opd` section of function descriptors, if section headers are present
+    pub fn opd(&self) -> Option<(u64, u64)> {
+        self.sections
+            .iter()
+            .find(|section| section.name == ".opd")
+            .map(|section| (section.address, section.size))
+    }
+
+    /// Returns every symbol found in the symbol tables and import/export stubs
+    pub fn symbols(&self) -> Result<Vec<Symbol>> {
+        let mut symbols = self.table_symbols()?;
+
+        if let Some(tables) = self.module_tables()? {
+            symbols.extend(self.imports(tables.imports)?);
+            symbols.extend(self.exports(tables.exports)?);
+        }
+
+        Ok(symbols)
+    }
+
+    fn table_symbols(&self) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        let opd = self.opd();
+
+        let tables = self.sections.iter().filter(|section| {
+            section.kind == SECTION_SYMBOL_TABLE || section.kind == SECTION_DYNAMIC_SYMBOL_TABLE
+        });
+
+        for table in tables {
+            let names = match self.sections.get(table.link as usize) {
+                Some(names) => names.offset,
+                None => continue,
+            };
+
+            for index in 1..(table.size / SYMBOL_SIZE) {
+                let entry = entry_offset(&self.data, table.offset, index, SYMBOL_SIZE)?;
+                let kind = match self.data.get(entry as usize + 4).map(|info| info & 0xF) {
+                    Some(SYMBOL_FUNCTION) => SymbolKind::Function,
+                    Some(SYMBOL_OBJECT) => SymbolKind::Object,
+                    _ => continue,
+                };
+
+                let name = read_string(
+                    &self.data,
+                    add_offset(names, read_u32(&self.data, entry)? as u64)?,
+                );
+                let mut address = read_u64(&self.data, entry + 0x08)?;
+                let size = read_u64(&self.data, entry + 0x10)?;
+
+                if name.is_empty() || address == 0 {
+                    continue;
+                }
+
+                // Function symbols name their descriptor; point them at the code instead
+                if let Some((opd_address, opd_size)) = opd {
+                    if address >= opd_address && address - opd_address < opd_size {
+                        address = self.descriptor(address)?.function;
+                    }
+                }
+
+                symbols.push(Symbol {
+                    name,
+                    address,
+                    size,
+                    kind,
+                });
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    /// Address of the `sys_prx_module_info` structure of a PRX
+    fn module_info(&self) -> Result<u64> {
+        match self.segments.first() {
+            // The physical address of the first segment holds the file offset of the info
+            Some(segment) => segment
+                .address
+                .checked_add(segment.physical_address)
+                .and_then(|address| address.checked_sub(segment.offset))
+                .context("module info address of the first segment is out of range"),
+            None => bail!("module has no segments"),
+        }
+    }
+
+    fn module_tables(&self) -> Result<Option<ModuleTables>> {
+        if self.is_prx() {
+            let info = self.module_info()?;
+
+            return Ok(Some(ModuleTables {
+                exports: (
+                    self.read_u32_at(add_offset(info, 0x24)?)? as u64,
+                    self.read_u32_at(add_offset(info, 0x28)?)? as u64,
+                ),
+                imports: (
+                    self.read_u32_at(add_offset(info, 0x2C)?)? as u64,
+                    self.read_u32_at(add_offset(info, 0x30)?)? as u64,
+                ),
+            }));
+        }
+
+        let param = match self
+            .segments
+            .iter()
+            .find(|segment| segment.kind == SEGMENT_PROCESS_PARAM)
+        {
+            Some(segment) => segment.offset,
+            None => return Ok(None),
+        };
+
+        if read_u32(&self.data, add_offset(param, 0x04)?)? != PRX_INFO_MAGIC {
+            return Ok(None);
+        }
+
+        Ok(Some(ModuleTables {
+            exports: (
+                read_u32(&self.data, add_offset(param, 0x10)?)? as u64,
+                read_u32(&self.data, add_offset(param, 0x14)?)? as u64,
+            ),
+            imports: (
+                read_u32(&self.data, add_offset(param, 0x18)?)? as u64,
+                read_u32(&self.data, add_offset(param, 0x1C)?)? as u64,
+            ),
+        }))
+    }
+
+    fn imports(&self, (start, end): (u64, u64)) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        let mut stub = start;
+
+        while stub < end {
+            let size = match self.read(stub, 1) {
+                Some([size]) if *size != 0 => *size as u64,
+                _ => IMPORT_SIZE,
+            };
+
+            let count = (self.read_u32_at(add_offset(stub, 0x04)?)? & 0xFFFF) as u64;
+            let library = self.string_at(self.read_u32_at(add_offset(stub, 0x10)?)? as u64);
+            let nids = self.read_u32_at(add_offset(stub, 0x14)?)? as u64;
+            let slots = self.read_u32_at(add_offset(stub, 0x18)?)? as u64;
+
+            for index in 0..count {
+                let nid = self.read_u32_at(add_offset(nids, index * 4)?)?;
+
+                symbols.push(Symbol {
+                    name: format!("{library}::{nid:08X}"),
+                    address: self.read_u32_at(add_offset(slots, index * 4)?)? as u64,
+                    size: 4,
+                    kind: SymbolKind::Import,
+                });
+            }
+
+            stub = add_offset(stub, size)?;
+        }
+
+        Ok(symbols)
+    }
+
+    fn exports(&self, (start, end): (u64, u64)) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        let mut entry = start;
+
+        while entry < end {
+            let size = match self.read(entry, 1) {
+                Some([size]) if *size != 0 => *size as u64,
+                _ => EXPORT_SIZE,
+            };
+
+            let count = (self.read_u32_at(add_offset(entry, 0x04)?)? & 0xFFFF) as u64;
+            let library = match self.read_u32_at(add_offset(entry, 0x10)?)? {
+                // The module's own anonymous library holds module_start and friends
+                0 => "module".to_string(),
+                name => self.string_at(name as u64),
+            };
+            let nids = self.read_u32_at(add_offset(entry, 0x14)?)? as u64;
+            let descriptors = self.read_u32_at(add_offset(entry, 0x18)?)? as u64;
+
+            for index in 0..count {
+                let nid = self.read_u32_at(add_offset(nids, index * 4)?)?;
+                let descriptor = self.read_u32_at(add_offset(descriptors, index * 4)?)? as u64;
+
+                symbols.push(Symbol {
+                    name: format!("{library}::{nid:08X}"),
+                    address: self.descriptor(descriptor)?.function,
+                    size: 0,
+                    kind: SymbolKind::Export,
+                });
+            }
+
+            entry = add_offset(entry, size)?;
+        }
+
+        Ok(symbols)
+    }
+
+    fn string_at(&self, address: u64) -> String {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.kind == SEGMENT_LOAD && segment.contains(address));
+
+        match segment.and_then(|segment| segment.offset.checked_add(address - segment.address)) {
+            Some(offset) => read_string(&self.data, offset),
+            None => format!("{address:#x}"),
+        }
+    }
+}
+
+/// Maps addresses in console memory back to `symbol+offset`
+#[derive(Debug, Clone, Default)]
+pub struct SymbolMap {
+    symbols: BTreeMap<u64, Symbol>,
+}
+
+impl SymbolMap {
+    pub fn new() -> Self {
+        SymbolMap::default()
+    }
+
+    /// Adds the symbols of an ELF file loaded at `base`
+    ///
+    /// ### Arguments
+    ///
+    /// * `elf` - The parsed file
+    /// * `base` - Load address of a PRX module, or 0 for executables
+    pub fn add_elf(&mut self, elf: &Elf, base: u64) -> Result<()> {
+        for mut symbol in elf.symbols()? {
+            symbol.address = add_offset(symbol.address, base)?;
+            self.insert(symbol);
+        }
+
+        Ok(())
+    }
+
+    /// Loads the symbols of an executable
+    pub fn from_elf(elf: &Elf) -> Result<Self> {
+        let mut symbol_map = SymbolMap::new();
+        symbol_map.add_elf(elf, 0)?;
+        Ok(symbol_map)
+    }
+
+    /// Adds a symbol, replacing any symbol at the same address
+    pub fn insert(&mut self, symbol: Symbol) {
+        self.symbols.insert(symbol.address, symbol);
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Returns the symbol containing an address and the offset into it
+    ///
+    /// Symbols of unknown size are assumed to extend up to the next symbol.
+    pub fn lookup(&self, address: u64) -> Option<(&Symbol, u64)> {
+        let (start, symbol) = self.symbols.range(..=address).next_back()?;
+        let offset = address - start;
+
+        match symbol.size == 0 || offset < symbol.size {
+            true => Some((symbol, offset)),
+            false => None,
+        }
+    }
+
+    /// Describes an address as `symbol+0x10`, or plain hex when no symbol contains it
+    pub fn describe(&self, address: u64) -> String {
+        SymbolAddress {
+            symbol_map: self,
+            address,
+        }
+        .to_string()
+    }
+}
+
+struct SymbolAddress<'a> {
+    symbol_map: &'a SymbolMap,
+    address: u64,
+}
+
+impl fmt::Display for SymbolAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.symbol_map.lookup(self.address) {
+            Some((symbol, 0)) => write!(f, "{}", symbol.name),
+            Some((symbol, offset)) => write!(f, "{}+{offset:#x}", symbol.name),
+            None => write!(f, "{:#x}", self.address),
+        }
+    }
+}
+
+/// Adds an offset to a file offset or address taken from the headers
+fn add_offset(base: u64, offset: u64) -> Result<u64> {
+    match base.checked_add(offset) {
+        Some(sum) => Ok(sum),
+        None => bail!("offset '{offset:#x}' from '{base:#x}' overflows"),
+    }
+}
+
+/// Returns the file offset of an entry of a table of fixed-size entries, checking that
+/// the whole entry is within the file
+fn entry_offset(data: &[u8], table: u64, index: u64, size: u64) -> Result<u64> {
+    let start = index
+        .checked_mul(size)
+        .and_then(|offset| table.checked_add(offset));
+
+    match start {
+        Some(start)
+            if start
+                .checked_add(size)
+                .map_or(false, |end| end <= data.len() as u64) =>
+        {
+            Ok(start)
+        }
+        _ => bail!("entry {index} of the table at '{table:#x}' is past the end of the file"),
+    }
+}
+
+fn read_bytes(data: &[u8], offset: u64, size: u64) -> Result<&[u8]> {
+    let range = usize::try_from(offset)
+        .ok()
+        .and_then(|start| Some(start..start.checked_add(size as usize)?));
+
+    match range.and_then(|range| data.get(range)) {
+        Some(bytes) => Ok(bytes),
+        None => bail!("offset '{offset:#x}' is past the end of the file"),
+    }
+}
+
+fn read_u16(data: &[u8], offset: u64) -> Result<u16> {
+    Ok(u16::from_be_bytes(read_bytes(data, offset, 2)?.try_into()?))
+}
+
+fn read_u32(data: &[u8], offset: u64) -> Result<u32> {
+    Ok(u32::from_be_bytes(read_bytes(data, offset, 4)?.try_into()?))
+}
+
+fn read_u64(data: &[u8], offset: u64) -> Result<u64> {
+    Ok(u64::from_be_bytes(read_bytes(data, offset, 8)?.try_into()?))
+}
+
+fn read_string(data: &[u8], offset: u64) -> String {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+        .unwrap_or_default();
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
@@ -200,3 +200,22 @@ impl From<u32> for ConsoleError {
         }
     }
 }
+
+/// Returned when memory read back after a verified write differs from what was written
+#[derive(Error, Debug)]
+#[error("memory at '{address:#x}' for pid '{pid}' differs after writing at {} offset(s): {offsets:?}", offsets.len())]
+pub struct WriteVerifyError {
+    pub pid: u32,
+    pub address: u64,
+    /// Offsets from `address` of every byte that did not stick
+    pub offsets: Vec<usize>,
+}
+
+/// Returned when a line of a cheat code file or assembly source cannot be parsed
+#[derive(Error, Debug)]
+#[error("line {line}: {message}")]
+pub struct CodeParseError {
+    /// Line number, starting at 1
+    pub line: usize,
+    pub message: String,
+}
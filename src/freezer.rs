@@ -0,0 +1,168 @@
+use crate::worker::{StopSignal, Worker};
+use crate::CCAPI;
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A value held fixed in process memory by a [Freezer](crate::freezer::Freezer)
+#[derive(Debug, Clone)]
+pub struct FreezeEntry {
+    pub pid: u32,
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    pub enabled: bool,
+}
+
+type EntryMap = Arc<Mutex<BTreeMap<usize, FreezeEntry>>>;
+
+/// Rewrites a set of memory values at a fixed interval on a background thread
+///
+/// Entries whose process no longer appears in the console process list are removed.
+/// The freezer stops on its own once removing them leaves no enabled entries.
+pub struct Freezer {
+    entries: EntryMap,
+    next_id: usize,
+    worker: Worker,
+}
+
+impl Freezer {
+    /// Starts a new freezer without any entries
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `interval` - How long to wait between each rewrite of the entries
+    pub fn start(ccapi: &CCAPI, interval: Duration) -> Self {
+        let entries = EntryMap::default();
+        let thread_entries = Arc::clone(&entries);
+        let ccapi = ccapi.clone();
+
+        let worker =
+            Worker::spawn(move |signal| freeze_loop(&ccapi, &thread_entries, interval, signal));
+
+        Freezer {
+            entries,
+            next_id: 0,
+            worker,
+        }
+    }
+
+    /// Adds an enabled entry and returns its identifier
+    pub fn add(&mut self, pid: u32, address: u64, bytes: Vec<u8>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let entry = FreezeEntry {
+            pid,
+            address,
+            bytes,
+            enabled: true,
+        };
+
+        self.entries.lock().unwrap().insert(id, entry);
+
+        id
+    }
+
+    /// Removes an entry, returning it if it existed
+    pub fn remove(&mut self, id: usize) -> Option<FreezeEntry> {
+        self.entries.lock().unwrap().remove(&id)
+    }
+
+    /// Enables or disables rewriting of an entry
+    pub fn set_enabled(&self, id: usize, enabled: bool) -> Result<()> {
+        match self.entries.lock().unwrap().get_mut(&id) {
+            Some(entry) => entry.enabled = enabled,
+            None => bail!("No freeze entry with id '{id}' exists"),
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the bytes written for an entry
+    pub fn set_bytes(&self, id: usize, bytes: Vec<u8>) -> Result<()> {
+        match self.entries.lock().unwrap().get_mut(&id) {
+            Some(entry) => entry.bytes = bytes,
+            None => bail!("No freeze entry with id '{id}' exists"),
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy of all entries along with their identifiers
+    pub fn entries(&self) -> Vec<(usize, FreezeEntry)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .map(|(id, entry)| (*id, entry.clone()))
+            .collect()
+    }
+
+    /// Returns whether the background thread is still rewriting values
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops the freezer, returning the error that ended it early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn freeze_loop(
+    ccapi: &CCAPI,
+    entries: &EntryMap,
+    interval: Duration,
+    signal: &StopSignal,
+) -> Result<()> {
+    loop {
+        let current: Vec<(usize, FreezeEntry)> = entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, entry)| (*id, entry.clone()))
+            .collect();
+
+        if !current.is_empty() {
+            let pids = ccapi.get_process_list()?;
+            let mut exited = Vec::new();
+
+            for (id, entry) in &current {
+                if !pids.contains(&entry.pid) {
+                    exited.push(*id);
+                    continue;
+                }
+
+                if !entry.enabled {
+                    continue;
+                }
+
+                if let Err(e) = ccapi.write_process_memory(&entry.pid, &entry.address, &entry.bytes)
+                {
+                    // The process may have exited since the list was fetched
+                    match ccapi.get_process_list()?.contains(&entry.pid) {
+                        true => return Err(e),
+                        false => exited.push(*id),
+                    }
+                }
+            }
+
+            if !exited.is_empty() {
+                let mut entries = entries.lock().unwrap();
+
+                for id in exited {
+                    entries.remove(&id);
+                }
+
+                if !entries.values().any(|entry| entry.enabled) {
+                    return Ok(());
+                }
+            }
+        }
+
+        if !signal.sleep(interval) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Identifying the game running in a process
+//!
+//! The title ID of HDD games and disc game updates is part of the process path, such as
+//! `/dev_hdd0/game/BLUS30001/USRDIR/EBOOT.BIN`. Games booted from `/dev_bdvd` carry no
+//! title ID in their path, so the `PARAM.SFO` of the inserted disc has to be supplied.
+
+use crate::process::title_id_from_path;
+use crate::sfo::ParamSfo;
+use crate::CCAPI;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+const DISC_PREFIX: &str = "/dev_bdvd/";
+const HDD_GAME_PREFIX: &str = "/dev_hdd0/game/";
+
+/// Where a process was launched from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameSource {
+    /// `/dev_hdd0/game/<ID>/...`
+    Hdd,
+    /// `/dev_bdvd/...`
+    Disc,
+    Other,
+}
+
+/// What is known about the game running in a process
+#[derive(Debug, Clone)]
+pub struct GameInfo {
+    pub pid: u32,
+    /// Executable path, as returned by [CCAPI::get_process_name]
+    pub path: String,
+    pub source: GameSource,
+    pub title_id: Option<String>,
+    pub title: Option<String>,
+    /// `APP_VER` of the title
+    pub version: Option<String>,
+    pub category: Option<String>,
+}
+
+/// Resolves process paths to [GameInfo] using locally supplied `PARAM.SFO` data
+#[derive(Debug, Clone, Default)]
+pub struct GameInfoResolver {
+    titles: HashMap<String, ParamSfo>,
+    disc: Option<ParamSfo>,
+}
+
+impl GameInfoResolver {
+    pub fn new() -> Self {
+        GameInfoResolver::default()
+    }
+
+    /// Adds the metadata of a title, keyed by its `TITLE_ID`
+    pub fn add(&mut self, sfo: ParamSfo) -> Result<()> {
+        let title_id = match sfo.title_id() {
+            Some(title_id) => title_id.to_string(),
+            None => bail!("PARAM.SFO has no TITLE_ID"),
+        };
+
+        self.titles.insert(title_id, sfo);
+
+        Ok(())
+    }
+
+    /// Sets the metadata of the disc in the drive, used for processes under `/dev_bdvd`
+    pub fn set_disc(&mut self, sfo: Option<ParamSfo>) {
+        self.disc = sfo;
+    }
+
+    /// Builds the [GameInfo] for a process from its executable path
+    pub fn resolve_path(&self, pid: u32, path: &str) -> GameInfo {
+        let (source, title_id) = match path {
+            _ if path.starts_with(DISC_PREFIX) => (
+                GameSource::Disc,
+                self.disc
+                    .as_ref()
+                    .and_then(|disc| disc.title_id())
+                    .map(String::from),
+            ),
+            _ if path.starts_with(HDD_GAME_PREFIX) => (GameSource::Hdd, title_id_from_path(path)),
+            _ => (GameSource::Other, title_id_from_path(path)),
+        };
+
+        let sfo = match (&title_id, source) {
+            (_, GameSource::Disc) => self.disc.as_ref(),
+            (Some(title_id), _) => self.titles.get(title_id),
+            (None, _) => None,
+        };
+
+        let field = |get: fn(&ParamSfo) -> Option<&str>| sfo.and_then(get).map(String::from);
+
+        GameInfo {
+            pid,
+            path: path.to_string(),
+            source,
+            title: field(ParamSfo::title),
+            version: field(ParamSfo::app_version),
+            category: field(ParamSfo::category),
+            title_id,
+        }
+    }
+
+    /// Looks up the path of a process on the console and resolves it
+    pub fn resolve(&self, ccapi: &CCAPI, pid: u32) -> Result<GameInfo> {
+        let path = ccapi.get_process_name(&pid)?;
+        Ok(self.resolve_path(pid, &path))
+    }
+}
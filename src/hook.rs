@@ -0,0 +1,283 @@
+//! Hooking game functions by redirecting them through a code cave
+//!
+//! Installing a hook overwrites the first instruction of the target with a branch to the
+//! cave. The cave receives the detour code, followed by the relocated original instruction
+//! and a branch back into the target:
+//!
+//! ```text
+//! target:     b cave              cave:        <detour>
+//! target + 4: ...                 trampoline:  <original instruction>
+//!                                              b target + 4
+//! ```
+//!
+//! A detour that falls through therefore runs before the original function, while one that
+//! returns with `blr` replaces it entirely. [Hook::install] assembles the detour once the
+//! cave address is known, so it may branch back into the game with relative branches.
+
+use crate::assembler::{self, INSTRUCTION_SIZE};
+use crate::regions::read_mapped;
+use crate::{MemoryRegion, CCAPI};
+use anyhow::{bail, Result};
+
+/// Most words a relocated instruction can expand to
+const MAX_RELOCATED_WORDS: u32 = 3;
+
+/// Where a hook places its detour and trampoline
+#[derive(Debug, Clone, Copy)]
+pub enum CodeCave {
+    /// Use unused memory starting at this address
+    At(u64),
+    /// Use the first zero-filled run in this region that is large enough
+    Search(MemoryRegion),
+}
+
+impl CodeCave {
+    /// Returns the address of a cave of at least `size` bytes, searching for one if needed
+    pub fn resolve(&self, ccapi: &CCAPI, pid: u32, size: u32) -> Result<u64> {
+        match *self {
+            CodeCave::At(address) => Ok(address),
+            CodeCave::Search(region) => match find_code_cave(ccapi, pid, &region, size)? {
+                Some(address) => Ok(address),
+                None => bail!(
+                    "no code cave of {size:#x} bytes found between '{:#x}' and '{:#x}'",
+                    region.address,
+                    region.end()
+                ),
+            },
+        }
+    }
+}
+
+/// An installed function hook
+///
+/// The target and cave are restored when the hook is dropped, or explicitly with
+/// [`Hook::unhook`].
+#[derive(Debug)]
+pub struct Hook {
+    ccapi: CCAPI,
+    pid: u32,
+    target: u64,
+    cave: u64,
+    trampoline: u64,
+    original_target: Vec<u8>,
+    original_cave: Vec<u8>,
+    installed: bool,
+}
+
+impl Hook {
+    /// Installs a hook on the function at `target`, assembling the detour for the cave
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `pid` - The process containing the function
+    /// * `target` - Address of the first instruction of the function
+    /// * `detour` - Assembly source of the code to run instead of the first instruction,
+    ///   see [assembler](crate::assembler)
+    /// * `cave` - Where to place the detour and trampoline
+    pub fn install(
+        ccapi: &CCAPI,
+        pid: u32,
+        target: u64,
+        detour: &str,
+        cave: CodeCave,
+    ) -> Result<Self> {
+        let size = assembler::assembled_size(detour)? as u32 + (MAX_RELOCATED_WORDS + 1) * 4;
+        let cave = cave.resolve(ccapi, pid, size)?;
+        let detour = assembler::assemble(detour, cave)?;
+
+        Hook::install_at(ccapi, pid, target, cave, &detour)
+    }
+
+    /// Installs a hook on the function at `target` with an already assembled detour
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `pid` - The process containing the function
+    /// * `target` - Address of the first instruction of the function
+    /// * `cave` - Unused memory for the detour and trampoline
+    /// * `detour` - Code to run instead of the first instruction, assembled for `cave`
+    pub fn install_at(
+        ccapi: &CCAPI,
+        pid: u32,
+        target: u64,
+        cave: u64,
+        detour: &[u8],
+    ) -> Result<Self> {
+        if target % INSTRUCTION_SIZE != 0
+            || cave % INSTRUCTION_SIZE != 0
+            || detour.len() as u64 % INSTRUCTION_SIZE != 0
+        {
+            bail!("hook target, cave and detour must be aligned to instructions");
+        }
+
+        let original_target = ccapi.read_process_memory(&pid, &target, &4)?;
+        let original_word = u32::from_be_bytes([
+            original_target[0],
+            original_target[1],
+            original_target[2],
+            original_target[3],
+        ]);
+
+        let trampoline = cave + detour.len() as u64;
+        let mut words = relocate(original_word, target, trampoline)?;
+        let resume = trampoline + words.len() as u64 * INSTRUCTION_SIZE;
+        words.push(assembler::encode_branch(
+            resume,
+            target + INSTRUCTION_SIZE,
+            false,
+        )?);
+
+        let mut cave_bytes = detour.to_vec();
+        cave_bytes.extend(words.iter().flat_map(|word| word.to_be_bytes().to_vec()));
+
+        let entry = assembler::encode_branch(target, cave, false)?;
+        let original_cave = ccapi.read_process_memory(&pid, &cave, &(cave_bytes.len() as u32))?;
+
+        // The cave must be complete before anything can branch into it
+        ccapi.write_process_memory(&pid, &cave, &cave_bytes)?;
+        ccapi.write_process_memory(&pid, &target, &entry.to_be_bytes())?;
+
+        Ok(Hook {
+            ccapi: ccapi.clone(),
+            pid,
+            target,
+            cave,
+            trampoline,
+            original_target,
+            original_cave,
+            installed: true,
+        })
+    }
+
+    /// Address of the hooked function
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    /// Address the detour was written to
+    pub fn cave(&self) -> u64 {
+        self.cave
+    }
+
+    /// Address of the relocated original instruction, which continues into the function
+    pub fn trampoline(&self) -> u64 {
+        self.trampoline
+    }
+
+    /// Returns true until the hook has been removed
+    pub fn is_installed(&self) -> bool {
+        self.installed
+    }
+
+    /// Restores the original instruction and the previous contents of the cave
+    pub fn unhook(&mut self) -> Result<()> {
+        if !self.installed {
+            return Ok(());
+        }
+
+        // Stop branching into the cave before clearing it
+        self.ccapi
+            .write_process_memory(&self.pid, &self.target, &self.original_target)?;
+        self.ccapi
+            .write_process_memory(&self.pid, &self.cave, &self.original_cave)?;
+
+        self.installed = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        let _ = self.unhook();
+    }
+}
+
+/// Returns the address of the first zero-filled, instruction aligned run of `size` bytes
+pub fn find_code_cave(
+    ccapi: &CCAPI,
+    pid: u32,
+    region: &MemoryRegion,
+    size: u32,
+) -> Result<Option<u64>> {
+    for (address, memory) in read_mapped(ccapi, pid, region)? {
+        if let Some(offset) = find_zero_run(&memory, address, size) {
+            return Ok(Some(address + offset as u64));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the offset of the first zero-filled, instruction aligned run in memory read from `address`
+fn find_zero_run(memory: &[u8], address: u64, size: u32) -> Option<usize> {
+    let start = (address % INSTRUCTION_SIZE) as usize;
+    let alignment = match start {
+        0 => 0,
+        misaligned => INSTRUCTION_SIZE as usize - misaligned,
+    };
+
+    let mut run_start = None;
+
+    for offset in (alignment..memory.len()).step_by(INSTRUCTION_SIZE as usize) {
+        let zero = memory[offset..]
+            .iter()
+            .take(INSTRUCTION_SIZE as usize)
+            .all(|byte| *byte == 0);
+
+        match (zero, run_start) {
+            (true, None) => run_start = Some(offset),
+            (false, Some(_)) => run_start = None,
+            _ => {}
+        }
+
+        if let Some(run) = run_start {
+            if offset + INSTRUCTION_SIZE as usize - run >= size as usize {
+                return Some(run);
+            }
+        }
+    }
+
+    None
+}
+
+/// Rewrites an instruction moved from `from` to `to`, fixing up relative branches
+///
+/// Conditional branches that no longer reach their target are expanded into a conditional
+/// branch over an unconditional one, so up to three words may be returned.
+pub fn relocate(word: u32, from: u64, to: u64) -> Result<Vec<u32>> {
+    let absolute = word & 2 != 0;
+    let link = word & 1 != 0;
+
+    match word >> 26 {
+        18 if !absolute => {
+            let offset = ((word & 0x03FF_FFFC) << 6) as i32 >> 6;
+            let destination = from.wrapping_add(offset as i64 as u64);
+
+            Ok(vec![assembler::encode_branch(to, destination, link)?])
+        }
+        16 if !absolute => {
+            let offset = (word & 0xFFFC) as u16 as i16 as i64;
+            let destination = from.wrapping_add(offset as u64);
+            let relocated = destination.wrapping_sub(to) as i64;
+
+            if (-0x8000..0x8000).contains(&relocated) {
+                return Ok(vec![word & !0xFFFC | (relocated as u32 & 0xFFFC)]);
+            }
+
+            if link {
+                bail!("conditional call at '{from:#x}' cannot be relocated to '{to:#x}'");
+            }
+
+            // bc <condition>, +8; b +8; b <destination>
+            Ok(vec![
+                word & !0xFFFC | 8,
+                assembler::encode_branch(to + 4, to + 12, false)?,
+                assembler::encode_branch(to + 8, destination, false)?,
+            ])
+        }
+        _ => Ok(vec![word]),
+    }
+}
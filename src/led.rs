@@ -0,0 +1,193 @@
+//! Playing sequences of console LED states in the background
+
+use crate::worker::{StopSignal, Worker};
+use crate::{ConsoleLed, LedStatus, CCAPI};
+use anyhow::{bail, ensure, Result};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Sets one LED and holds it for a duration before the next step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedStep {
+    pub color: ConsoleLed,
+    pub status: LedStatus,
+    pub duration: Duration,
+}
+
+impl LedStep {
+    pub fn new(color: ConsoleLed, status: LedStatus, duration: Duration) -> Self {
+        LedStep {
+            color,
+            status,
+            duration,
+        }
+    }
+}
+
+/// Status of both console LEDs
+///
+/// The console does not report its LED status, so a state to go back to after an
+/// [LedSequence] has to be the one the caller last set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedState {
+    pub red: LedStatus,
+    pub green: LedStatus,
+}
+
+impl LedState {
+    pub fn new(red: LedStatus, green: LedStatus) -> Self {
+        LedState { red, green }
+    }
+
+    /// Sets both LEDs on the console
+    pub fn apply(&self, ccapi: &CCAPI) -> Result<()> {
+        ccapi.set_console_led(ConsoleLed::Red, self.red)?;
+        ccapi.set_console_led(ConsoleLed::Green, self.green)
+    }
+}
+
+/// Built-in sequences for reporting the status of a test run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedPreset {
+    /// Alternates between the red and green LED until stopped
+    TestRunning,
+    /// Turns the green LED off and blinks the red LED until the LEDs are set again
+    Failed,
+    /// Turns the red LED off and blinks the green LED until the LEDs are set again
+    Passed,
+}
+
+impl FromStr for LedPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "test running" | "test-running" | "running" => Ok(LedPreset::TestRunning),
+            "failed" => Ok(LedPreset::Failed),
+            "passed" => Ok(LedPreset::Passed),
+            _ => bail!("invalid LED preset '{s}' provided"),
+        }
+    }
+}
+
+/// A list of [LedStep]s played once or in a loop on a background thread
+///
+/// When playback is stopped, the LEDs are set to the state given to
+/// [restore](LedSequence::restore). Without one, or when a sequence that doesn't repeat
+/// finishes on its own, the last step played stays applied.
+#[derive(Debug, Clone)]
+pub struct LedSequence {
+    steps: Vec<LedStep>,
+    repeat: bool,
+    restore: Option<LedState>,
+}
+
+impl LedSequence {
+    pub fn new(steps: Vec<LedStep>) -> Self {
+        LedSequence {
+            steps,
+            repeat: false,
+            restore: None,
+        }
+    }
+
+    /// Builds one of the built-in sequences
+    pub fn preset(preset: LedPreset) -> Self {
+        let step =
+            |color, status, millis| LedStep::new(color, status, Duration::from_millis(millis));
+
+        match preset {
+            LedPreset::TestRunning => LedSequence::new(vec![
+                step(ConsoleLed::Red, LedStatus::Off, 0),
+                step(ConsoleLed::Green, LedStatus::On, 500),
+                step(ConsoleLed::Green, LedStatus::Off, 0),
+                step(ConsoleLed::Red, LedStatus::On, 500),
+            ])
+            .repeat(true),
+            LedPreset::Failed => LedSequence::new(vec![
+                step(ConsoleLed::Green, LedStatus::Off, 0),
+                step(ConsoleLed::Red, LedStatus::Blink, 0),
+            ]),
+            LedPreset::Passed => LedSequence::new(vec![
+                step(ConsoleLed::Red, LedStatus::Off, 0),
+                step(ConsoleLed::Green, LedStatus::Blink, 0),
+            ]),
+        }
+    }
+
+    /// Sets whether the steps are played in a loop until stopped
+    pub fn repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Sets the state applied when playback is stopped, such as the state set before
+    /// playing, or `None` to leave the last step applied
+    pub fn restore(mut self, restore: Option<LedState>) -> Self {
+        self.restore = restore;
+        self
+    }
+
+    /// Starts playing the steps on a background thread
+    pub fn play(self, ccapi: &CCAPI) -> Result<LedHandle> {
+        ensure!(!self.steps.is_empty(), "LED sequence has no steps");
+        ensure!(
+            !self.repeat
+                || self
+                    .steps
+                    .iter()
+                    .any(|step| step.duration > Duration::from_secs(0)),
+            "repeating LED sequence must have at least one step with a duration"
+        );
+
+        let ccapi = ccapi.clone();
+        let worker = Worker::spawn(move |signal| self.play_loop(&ccapi, signal));
+
+        Ok(LedHandle { worker })
+    }
+
+    fn play_loop(&self, ccapi: &CCAPI, signal: &StopSignal) -> Result<()> {
+        let stopped = self.play_steps(ccapi, signal)?;
+
+        match self.restore {
+            Some(restore) if stopped => restore.apply(ccapi),
+            _ => Ok(()),
+        }
+    }
+
+    /// Plays the steps, returning whether playback was stopped before it finished
+    fn play_steps(&self, ccapi: &CCAPI, signal: &StopSignal) -> Result<bool> {
+        loop {
+            for step in &self.steps {
+                ccapi.set_console_led(step.color, step.status)?;
+
+                if !signal.sleep(step.duration) {
+                    return Ok(true);
+                }
+            }
+
+            if !self.repeat {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Handle to a playing [LedSequence](crate::led::LedSequence)
+pub struct LedHandle {
+    worker: Worker,
+}
+
+impl LedHandle {
+    /// Returns whether the sequence is still playing
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops playback and restores the LEDs if a restore state was set and the sequence was
+    /// still playing, returning the error
+    /// that ended playback early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
@@ -1,22 +1,66 @@
 #![forbid(unsafe_code)]
 
+// Allows the derive macros to refer to `::ccapi` from within this crate
+extern crate self as ccapi;
+
+pub mod artemis;
+pub mod assembler;
+pub mod buzzer;
+pub mod cache;
+pub mod database;
+pub mod diff;
+pub mod disassembler;
+pub mod elf;
 mod errors;
+pub mod freezer;
+pub mod game;
+mod hex;
+pub mod hook;
+pub mod led;
+pub mod monitor;
+pub mod netcheat;
+pub mod notify;
+pub mod patch;
+pub mod process;
+pub mod progress;
+pub mod regions;
+pub mod rpc;
+pub mod sfo;
+pub mod snapshot;
+pub mod structs;
+pub mod watch;
+mod worker;
 
 use anyhow::{anyhow, bail, ensure, Error, Result};
-use errors::ConsoleError;
+use cache::ReadCache;
+pub use errors::{CodeParseError, ConsoleError, WriteVerifyError};
+use process::{Process, ProcessMap, PROCESS_POLL_INTERVAL};
+use regions::RegionProber;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use structs::ConsoleStruct;
 
 const CCAPI_OK: u32 = 0;
 const DEFAULT_CCAPI_PORT: u16 = 6333;
 const DEFAULT_RADIX: u32 = 16;
 
+/// Largest amount of memory requested from the console at once
+const MEMORY_CHUNK_SIZE: u32 = 0x1000;
+
+#[derive(Debug, Clone)]
 pub struct CCAPI {
     console_socket: SocketAddr,
+    read_cache: Option<Arc<ReadCache>>,
+    region_prober: Arc<RegionProber>,
+    verify_writes: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuzzerType {
     Continuous,
     Single,
@@ -66,7 +110,7 @@ impl ShutdownMode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NotifyIcon {
     Info,
     Caution,
@@ -147,7 +191,7 @@ impl FromStr for NotifyIcon {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConsoleLed {
     Red,
     Green,
@@ -174,7 +218,7 @@ impl ConsoleLed {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LedStatus {
     Off,
     On,
@@ -204,7 +248,7 @@ impl LedStatus {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ConsoleType {
     Unknown,
     CEX,
@@ -234,7 +278,7 @@ impl From<i32> for ConsoleType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FirmwareInfo {
     pub firmware_version: u32,
     pub ccapi_version: u32,
@@ -251,6 +295,52 @@ pub struct TemperatureInfo {
     pub rsx: i32,
 }
 
+/// A contiguous range of process memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub address: u64,
+    pub size: u64,
+}
+
+impl MemoryRegion {
+    pub fn new(address: u64, size: u64) -> Self {
+        MemoryRegion { address, size }
+    }
+
+    /// Returns the address directly after the region
+    pub fn end(&self) -> u64 {
+        self.address + self.size
+    }
+
+    /// Returns whether the address lies within the region
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.address && address < self.end()
+    }
+}
+
+impl FromStr for MemoryRegion {
+    type Err = anyhow::Error;
+
+    /// Parses a region in the `<address>:<size>` form, e.g. `0x10000:0x2000`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+
+        match (parts.next(), parts.next()) {
+            (Some(raw_address), Some(raw_size)) => {
+                let address = parse_hex_u64(raw_address)?;
+                let size = parse_hex_u64(raw_size)?;
+
+                if address.checked_add(size).is_none() {
+                    bail!("memory region '{s}' overflows the address space");
+                }
+
+                Ok(MemoryRegion::new(address, size))
+            }
+            _ => bail!("invalid memory region '{s}' provided, expected '<address>:<size>'"),
+        }
+    }
+}
+
 struct ConsoleRequest<'a> {
     socket: &'a SocketAddr,
     command: String,
@@ -345,7 +435,12 @@ impl CCAPI {
     pub fn new(console_ip: Ipv4Addr) -> Self {
         let console_socket = SocketAddr::new(IpAddr::V4(console_ip), DEFAULT_CCAPI_PORT);
 
-        CCAPI { console_socket }
+        CCAPI {
+            console_socket,
+            read_cache: None,
+            region_prober: Arc::new(RegionProber::new()),
+            verify_writes: false,
+        }
     }
 
     /// Sets the IPv4 address of the console to communicate with
@@ -358,6 +453,55 @@ impl CCAPI {
         self.console_socket.set_port(port);
     }
 
+    /// Sets whether every memory write is read back and compared, see
+    /// [write_process_memory_verified](crate::CCAPI::write_process_memory_verified)
+    pub fn set_verify_writes(&mut self, verify_writes: bool) {
+        self.verify_writes = verify_writes;
+    }
+
+    /// Enables caching of process memory reads
+    ///
+    /// Memory is cached in pages of [CACHE_PAGE_SIZE](crate::cache::CACHE_PAGE_SIZE) bytes,
+    /// which are served for `ttl` after being fetched. Clones of this instance share the cache.
+    ///
+    /// ### Arguments
+    ///
+    /// * `ttl` - How long a fetched page stays valid
+    pub fn enable_read_cache(&mut self, ttl: Duration) {
+        self.read_cache = Some(Arc::new(ReadCache::new(ttl)));
+    }
+
+    /// Disables caching of process memory reads, dropping all cached pages
+    pub fn disable_read_cache(&mut self) {
+        self.read_cache = None;
+    }
+
+    /// Returns the read cache, if enabled
+    pub fn read_cache(&self) -> Option<&ReadCache> {
+        self.read_cache.as_deref()
+    }
+
+    /// Returns the prober used to skip unmapped memory in scans and dumps
+    ///
+    /// Clones of this instance share the prober, so each page of a process is only probed
+    /// once. Call [RegionProber::invalidate](crate::regions::RegionProber::invalidate) after
+    /// a process maps or unmaps memory.
+    pub fn region_prober(&self) -> &RegionProber {
+        &self.region_prober
+    }
+
+    /// Drops cached memory of a process within the given range, if the read cache is enabled
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier the memory belongs to
+    /// * `range` - The memory range to invalidate
+    pub fn invalidate(&self, pid: &u32, range: MemoryRegion) {
+        if let Some(read_cache) = &self.read_cache {
+            read_cache.invalidate(*pid, range);
+        }
+    }
+
     /// Rings the console buzzer with the specified [BuzzerType](crate::BuzzerType)
     ///
     /// ### Arguments
@@ -501,27 +645,307 @@ impl CCAPI {
     }
 
     /// Returns a map of process ids and their names
-    pub fn get_process_map(&self) -> Result<HashMap<u32, String>> {
+    ///
+    /// A failed name lookup, such as for a process exiting while the map is built, does not
+    /// fail the whole map; the pid is listed in [ProcessMap::failed](crate::process::ProcessMap::failed) instead.
+    /// The names themselves are in [ProcessMap::names](crate::process::ProcessMap::names), which
+    /// holds what this method returned directly before it reported failed lookups.
+    pub fn get_process_map(&self) -> Result<ProcessMap> {
+        self.get_process_map_concurrent(1)
+    }
+
+    /// Returns a map of process ids and their names, looking names up in parallel
+    ///
+    /// ### Arguments
+    ///
+    /// * `connections` - The most name lookups the console is sent at once
+    pub fn get_process_map_concurrent(&self, connections: usize) -> Result<ProcessMap> {
         let pids = self.get_process_list()?;
-        let mut process_map = HashMap::new();
+        let connections = connections.min(pids.len()).max(1);
+        let queue = Arc::new(Mutex::new(pids));
+
+        let lookups = match connections {
+            1 => lookup_process_names(self, &queue),
+            _ => {
+                let handles: Vec<_> = (0..connections)
+                    .map(|_| {
+                        let ccapi = self.clone();
+                        let queue = Arc::clone(&queue);
+                        thread::spawn(move || lookup_process_names(&ccapi, &queue))
+                    })
+                    .collect();
+
+                let mut lookups = Vec::new();
+
+                for handle in handles {
+                    match handle.join() {
+                        Ok(thread_lookups) => lookups.extend(thread_lookups),
+                        Err(_) => bail!("A process name lookup thread panicked"),
+                    }
+                }
+
+                lookups
+            }
+        };
 
-        for pid in pids {
-            let process_name = self.get_process_name(&pid)?;
-            process_map.insert(pid, process_name);
+        let mut process_map = ProcessMap::default();
+
+        for (pid, process_name) in lookups {
+            match process_name {
+                Some(process_name) => {
+                    process_map.names.insert(pid, process_name);
+                }
+                None => process_map.failed.push(pid),
+            }
         }
 
+        process_map.failed.sort_unstable();
+
         Ok(process_map)
     }
 
-    /// **!! NOT IMPLEMENTED !!**
+    /// Returns a [Process](crate::process::Process) from its identifier (pid)
+    pub fn get_process(&self, pid: &u32) -> Result<Process> {
+        Ok(Process::new(*pid, &self.get_process_name(pid)?))
+    }
+
+    /// Returns every running [Process](crate::process::Process), sorted by pid
+    ///
+    /// Processes whose name could not be looked up, usually because they exited while the
+    /// list was built, are left out instead of failing the whole list.
+    pub fn get_processes(&self) -> Result<Vec<Process>> {
+        let mut processes: Vec<Process> = self
+            .get_process_map()?
+            .names
+            .iter()
+            .map(|(pid, name)| Process::new(*pid, name))
+            .collect();
+
+        processes.sort_unstable_by_key(|process| process.pid);
+
+        Ok(processes)
+    }
+
+    /// Returns the first process whose executable name or full path equals `name`
+    pub fn find_process_by_name(&self, name: &str) -> Result<Option<Process>> {
+        Ok(self
+            .get_processes()?
+            .into_iter()
+            .find(|process| process.name == name || process.path == name))
+    }
+
+    /// Returns the running game, which is the only process that is not system software
+    pub fn game_process(&self) -> Result<Option<Process>> {
+        Ok(self
+            .get_processes()?
+            .into_iter()
+            .find(|process| !process.is_system))
+    }
+
+    /// Polls the process list until a process matches the predicate
+    ///
+    /// ### Arguments
     ///
+    /// * `predicate` - Returns true for the wanted process
+    /// * `timeout` - How long to wait before giving up
+    pub fn wait_for_process<F>(&self, mut predicate: F, timeout: Duration) -> Result<Process>
+    where
+        F: FnMut(&Process) -> bool,
+    {
+        let start = Instant::now();
+
+        loop {
+            if let Some(process) = self.get_processes()?.into_iter().find(|p| predicate(p)) {
+                return Ok(process);
+            }
+
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                bail!("No matching process appeared within {timeout:?}");
+            }
+
+            thread::sleep(PROCESS_POLL_INTERVAL.min(timeout - elapsed));
+        }
+    }
+
     /// Read process memory from the given address
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier to read from
+    /// * `address` - The address to start reading at
+    /// * `size` - The amount of bytes to read
     pub fn read_process_memory(&self, pid: &u32, address: &u64, size: &u32) -> Result<Vec<u8>> {
-        let _response = ConsoleRequest::new(&self.console_socket, "getmemory")
+        match &self.read_cache {
+            Some(read_cache) => read_cache.read(*pid, *address, *size, |page| {
+                self.fetch_process_memory(pid, &page, &(cache::CACHE_PAGE_SIZE as u32))
+            }),
+            None => self.fetch_process_memory(pid, address, size),
+        }
+    }
+
+    fn fetch_process_memory(&self, pid: &u32, address: &u64, size: &u32) -> Result<Vec<u8>> {
+        let response = ConsoleRequest::new(&self.console_socket, "getmemory")
             .param("pid", &pid.to_string())
             .param("addr", &format!("{address:#4x}"))
-            .param("size", &size.to_string());
+            .param("size", &size.to_string())
+            .send()?;
+
+        let raw_memory = response.lines.get(1).ok_or(anyhow!(
+            "Could not read memory at '{address:#x}' for pid '{pid}'"
+        ))?;
+
+        let memory = decode_hex(raw_memory.trim())?;
+
+        ensure!(
+            memory.len() == *size as usize,
+            "expected {size} bytes of memory at '{address:#x}', received {}",
+            memory.len()
+        );
 
-        unimplemented!("read_process_memory is not implemented")
+        Ok(memory)
     }
+
+    /// Writes bytes to process memory at the given address
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier to write to
+    /// * `address` - The address to start writing at
+    /// * `bytes` - The bytes to write
+    pub fn write_process_memory(&self, pid: &u32, address: &u64, bytes: &[u8]) -> Result<()> {
+        match self.verify_writes {
+            true => self.write_process_memory_verified(pid, address, bytes),
+            false => self.send_process_memory(pid, address, bytes),
+        }
+    }
+
+    /// Writes bytes to process memory, then reads them back to make sure they stuck
+    ///
+    /// A [WriteVerifyError](crate::WriteVerifyError) listing the differing offsets is
+    /// returned on a mismatch, e.g. when writing to read-only pages.
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier to write to
+    /// * `address` - The address to start writing at
+    /// * `bytes` - The bytes to write
+    pub fn write_process_memory_verified(
+        &self,
+        pid: &u32,
+        address: &u64,
+        bytes: &[u8],
+    ) -> Result<()> {
+        self.send_process_memory(pid, address, bytes)?;
+
+        // Read back directly from the console, bypassing the read cache
+        let memory = self.fetch_process_memory(pid, address, &(bytes.len() as u32))?;
+
+        let offsets: Vec<usize> = (0..bytes.len())
+            .filter(|offset| memory[*offset] != bytes[*offset])
+            .collect();
+
+        ensure!(
+            offsets.is_empty(),
+            WriteVerifyError {
+                pid: *pid,
+                address: *address,
+                offsets,
+            }
+        );
+
+        Ok(())
+    }
+
+    fn send_process_memory(&self, pid: &u32, address: &u64, bytes: &[u8]) -> Result<()> {
+        ConsoleRequest::new(&self.console_socket, "setmemory")
+            .param("pid", &pid.to_string())
+            .param("addr", &format!("{address:#4x}"))
+            .param("value", &encode_hex(bytes))
+            .send()?;
+
+        self.invalidate(pid, MemoryRegion::new(*address, bytes.len() as u64));
+
+        Ok(())
+    }
+
+    /// Reads a [ConsoleStruct](crate::structs::ConsoleStruct) with a single memory read
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier to read from
+    /// * `address` - The address the value starts at
+    pub fn read_struct<T: ConsoleStruct>(&self, pid: &u32, address: &u64) -> Result<T> {
+        let memory = self.read_process_memory(pid, address, &(T::SIZE as u32))?;
+        T::decode(&memory)
+    }
+
+    /// Writes a [ConsoleStruct](crate::structs::ConsoleStruct) with a single memory write
+    ///
+    /// Bytes not covered by any field are written as zeroes, use
+    /// [update_struct](crate::CCAPI::update_struct) to keep them intact.
+    ///
+    /// ### Arguments
+    ///
+    /// * `pid` - The process identifier to write to
+    /// * `address` - The address the value starts at
+    /// * `value` - The value to write
+    pub fn write_struct<T: ConsoleStruct>(
+        &self,
+        pid: &u32,
+        address: &u64,
+        value: &T,
+    ) -> Result<()> {
+        self.write_process_memory(pid, address, &value.to_bytes()?)
+    }
+
+    /// Writes a [ConsoleStruct](crate::structs::ConsoleStruct) over the current memory,
+    /// keeping bytes not covered by any field intact
+    pub fn update_struct<T: ConsoleStruct>(
+        &self,
+        pid: &u32,
+        address: &u64,
+        value: &T,
+    ) -> Result<()> {
+        let mut memory = self.read_process_memory(pid, address, &(T::SIZE as u32))?;
+        value.encode(&mut memory)?;
+        self.write_process_memory(pid, address, &memory)
+    }
+}
+
+/// Looks up the names of pids taken from a shared queue until it is empty
+fn lookup_process_names(ccapi: &CCAPI, queue: &Mutex<Vec<u32>>) -> Vec<(u32, Option<String>)> {
+    let mut lookups = Vec::new();
+
+    loop {
+        let pid = match queue.lock().unwrap().pop() {
+            Some(pid) => pid,
+            None => return lookups,
+        };
+
+        lookups.push((pid, ccapi.get_process_name(&pid).ok()));
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn parse_hex_u64(raw: &str) -> Result<u64> {
+    let digits = raw.trim_start_matches("0x").trim_start_matches("0X");
+    Ok(u64::from_str_radix(digits, DEFAULT_RADIX)?)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    ensure!(hex.len() % 2 == 0, "hex string '{hex}' has an odd length");
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .ok_or(anyhow!("hex string '{hex}' is not valid ASCII"))
+                .and_then(|byte| Ok(u8::from_str_radix(byte, DEFAULT_RADIX)?))
+        })
+        .collect()
 }
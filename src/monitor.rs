@@ -0,0 +1,145 @@
+use crate::process::Process;
+use crate::worker::{StopSignal, Worker};
+use crate::CCAPI;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change in the console process list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    Started(Process),
+    Exited(u32),
+}
+
+/// Polls the console process list and reports processes starting and exiting
+///
+/// Names are only requested for pids that were not seen on the previous poll.
+pub struct ProcessMonitor {
+    interval: Duration,
+    report_existing: bool,
+}
+
+impl Default for ProcessMonitor {
+    fn default() -> Self {
+        ProcessMonitor::new()
+    }
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        ProcessMonitor {
+            interval: DEFAULT_POLL_INTERVAL,
+            report_existing: false,
+        }
+    }
+
+    /// Sets how long to wait between each poll
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets whether processes already running on the first poll are reported as started
+    pub fn report_existing(mut self, report_existing: bool) -> Self {
+        self.report_existing = report_existing;
+        self
+    }
+
+    /// Starts polling on a background thread, calling `callback` for every event
+    pub fn spawn<F>(self, ccapi: &CCAPI, mut callback: F) -> MonitorHandle
+    where
+        F: FnMut(ProcessEvent) + Send + 'static,
+    {
+        self.spawn_with(ccapi, move |event| {
+            callback(event);
+            true
+        })
+    }
+
+    /// Starts polling on a background thread, sending every event over a channel
+    ///
+    /// Polling stops once the receiver is dropped.
+    pub fn spawn_channel(self, ccapi: &CCAPI) -> (MonitorHandle, Receiver<ProcessEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.spawn_with(ccapi, move |event| sender.send(event).is_ok());
+
+        (handle, receiver)
+    }
+
+    fn spawn_with<F>(self, ccapi: &CCAPI, emit: F) -> MonitorHandle
+    where
+        F: FnMut(ProcessEvent) -> bool + Send + 'static,
+    {
+        let ccapi = ccapi.clone();
+        let worker = Worker::spawn(move |signal| self.poll_loop(&ccapi, emit, signal));
+
+        MonitorHandle { worker }
+    }
+
+    fn poll_loop<F>(&self, ccapi: &CCAPI, mut emit: F, signal: &StopSignal) -> Result<()>
+    where
+        F: FnMut(ProcessEvent) -> bool,
+    {
+        let mut known: Option<HashSet<u32>> = None;
+
+        loop {
+            let mut pids: HashSet<u32> = ccapi.get_process_list()?.into_iter().collect();
+            let mut events = Vec::new();
+
+            let new_pids: Vec<u32> = match &known {
+                Some(known) => {
+                    for pid in known.difference(&pids) {
+                        events.push(ProcessEvent::Exited(*pid));
+                    }
+
+                    pids.difference(known).copied().collect()
+                }
+                None if self.report_existing => pids.iter().copied().collect(),
+                None => Vec::new(),
+            };
+
+            for pid in new_pids {
+                match ccapi.get_process(&pid) {
+                    Ok(process) => events.push(ProcessEvent::Started(process)),
+                    // Retried on the next poll, unless the process has exited by then
+                    Err(_) => {
+                        pids.remove(&pid);
+                    }
+                }
+            }
+
+            for event in events {
+                if !emit(event) {
+                    return Ok(());
+                }
+            }
+
+            known = Some(pids);
+
+            if !signal.sleep(self.interval) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Handle to a running [ProcessMonitor](crate::monitor::ProcessMonitor)
+pub struct MonitorHandle {
+    worker: Worker,
+}
+
+impl MonitorHandle {
+    /// Returns whether the monitor is still polling
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops polling, returning the error that ended the monitor early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
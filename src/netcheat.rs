@@ -0,0 +1,691 @@
+//! Parsing and executing NetCheat PS3 code lists
+//!
+//! A code list holds codes separated by lines containing only `#`. Each code starts with
+//! its name, optionally followed by a line holding `1` (constant write) or `0` (write once),
+//! and then one code line per instruction in the `<type> <address> <value>` form:
+//!
+//! | Type | Line(s)                                   | Effect                                                  |
+//! |------|-------------------------------------------|---------------------------------------------------------|
+//! | `0`  | `0 ADDRESS BYTES`                         | Write bytes                                             |
+//! | `1`  | `1 ADDRESS TEXT`                          | Write text                                              |
+//! | `2`  | `2 ADDRESS FLOAT`                         | Write a 32-bit float                                    |
+//! | `4`  | `4 ADDRESS BYTES` + `4 COUNT INCREMENT`   | Write bytes `COUNT` times, `INCREMENT` bytes apart      |
+//! | `6`  | `6 ADDRESS OFFSET`                        | Read the pointer at `ADDRESS` and add `OFFSET`          |
+//! | `B`  | `B START END` + `B FIND REPLACE`          | Replace every `FIND` between `START` and `END`          |
+//! | `D`  | `D ADDRESS BYTES`                         | Run the remaining lines only if memory equals `BYTES`   |
+//! | `E`  | `E ADDRESS MASK`                          | Run the remaining lines only if all `MASK` bits are set |
+//! | `F`  | `F SOURCE SIZE` + `F DESTINATION 0`       | Copy `SIZE` bytes from `SOURCE` to `DESTINATION`        |
+//!
+//! The address of a line directly following a pointer line (`6`) is an offset from the
+//! resolved pointer, which for `F` applies to both `SOURCE` and `DESTINATION`.
+//! Consecutive pointer lines follow a chain of pointers.
+
+use crate::regions::read_mapped;
+use crate::worker::Worker;
+use crate::{decode_hex, CodeParseError, MemoryRegion, CCAPI};
+use anyhow::{bail, Context, Result};
+use std::convert::{TryFrom, TryInto};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const CODE_SEPARATOR: &str = "#";
+
+/// A single instruction of a NetCheat code
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Write {
+        address: u64,
+        bytes: Vec<u8>,
+    },
+    WriteText {
+        address: u64,
+        text: String,
+    },
+    WriteFloat {
+        address: u64,
+        value: f32,
+    },
+    WriteCondensed {
+        address: u64,
+        bytes: Vec<u8>,
+        count: u32,
+        increment: u64,
+    },
+    Pointer {
+        address: u64,
+        offset: u64,
+    },
+    FindReplace {
+        start: u64,
+        end: u64,
+        find: Vec<u8>,
+        replace: Vec<u8>,
+    },
+    IfEqual {
+        address: u64,
+        bytes: Vec<u8>,
+    },
+    IfMask {
+        address: u64,
+        mask: Vec<u8>,
+    },
+    Copy {
+        source: u64,
+        size: u32,
+        destination: u64,
+    },
+}
+
+/// An operation along with the line it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeLine {
+    /// Line number of the first line of the operation, starting at 1
+    pub line: usize,
+    pub operation: Operation,
+}
+
+/// A named NetCheat code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+    pub name: String,
+    /// Whether the code is rewritten continuously in constant-write mode
+    pub constant: bool,
+    pub lines: Vec<CodeLine>,
+}
+
+impl Code {
+    /// Runs every line of the code once against a process
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to run the code on
+    /// * `pid` - The process identifier to run the code against
+    pub fn apply(&self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        run_lines(ccapi, pid, &self.name, &self.lines)
+    }
+}
+
+/// A list of NetCheat codes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeList {
+    pub codes: Vec<Code>,
+}
+
+impl CodeList {
+    /// Parses a code list, reporting the line number of the first invalid line
+    pub fn parse(source: &str) -> Result<Self, CodeParseError> {
+        let mut codes = Vec::new();
+        let mut block: Vec<(usize, &str)> = Vec::new();
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = raw_line.trim();
+
+            match line {
+                CODE_SEPARATOR => {
+                    if !block.is_empty() {
+                        codes.push(parse_code(&block)?);
+                        block.clear();
+                    }
+                }
+                "" => {}
+                _ => block.push((index + 1, line)),
+            }
+        }
+
+        if !block.is_empty() {
+            codes.push(parse_code(&block)?);
+        }
+
+        Ok(CodeList { codes })
+    }
+
+    /// Loads and parses a code list file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+
+        CodeList::parse(&source).with_context(|| format!("invalid code list {path:?}"))
+    }
+
+    /// Returns the code with the given name
+    pub fn get(&self, name: &str) -> Option<&Code> {
+        self.codes.iter().find(|code| code.name == name)
+    }
+
+    /// Runs every code once against a process
+    pub fn apply(&self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        self.codes
+            .iter()
+            .try_for_each(|code| code.apply(ccapi, pid))
+    }
+
+    /// Runs every code once, then keeps rewriting the constant codes on a background thread
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to run the codes on
+    /// * `pid` - The process identifier to run the codes against
+    /// * `interval` - How long to wait between each rewrite of the constant codes
+    pub fn run_constant(
+        &self,
+        ccapi: &CCAPI,
+        pid: u32,
+        interval: Duration,
+    ) -> Result<ConstantWriter> {
+        self.apply(ccapi, pid)?;
+
+        let constant_codes: Vec<Code> = self
+            .codes
+            .iter()
+            .filter(|code| code.constant)
+            .cloned()
+            .collect();
+        let ccapi = ccapi.clone();
+
+        let worker = Worker::spawn(move |signal| {
+            while signal.sleep(interval) {
+                for code in &constant_codes {
+                    code.apply(&ccapi, pid)?;
+                }
+            }
+
+            Ok(())
+        });
+
+        Ok(ConstantWriter { worker })
+    }
+}
+
+/// Handle to the background thread rewriting constant codes
+pub struct ConstantWriter {
+    worker: Worker,
+}
+
+impl ConstantWriter {
+    /// Returns whether constant codes are still being written
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops writing, returning the error that ended the writer early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+/// Runs the lines of a code in order, stopping at the first unmet condition
+pub(crate) fn run_lines(ccapi: &CCAPI, pid: u32, name: &str, lines: &[CodeLine]) -> Result<()> {
+    let mut pointer: Option<u64> = None;
+
+    for code_line in lines {
+        let run = run_operation(ccapi, pid, &code_line.operation, &mut pointer)
+            .with_context(|| format!("code '{name}', line {}", code_line.line))?;
+
+        if !run {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs an operation, returning `false` when the rest of the code must be skipped
+fn run_operation(
+    ccapi: &CCAPI,
+    pid: u32,
+    operation: &Operation,
+    pointer: &mut Option<u64>,
+) -> Result<bool> {
+    // Addresses directly following a pointer line are relative to the resolved pointer
+    let base = pointer.take().unwrap_or(0);
+
+    match operation {
+        Operation::Write { address, bytes } => {
+            ccapi.write_process_memory(&pid, &offset_address(base, *address)?, bytes)?;
+        }
+        Operation::WriteText { address, text } => {
+            let target = offset_address(base, *address)?;
+            ccapi.write_process_memory(&pid, &target, text.as_bytes())?;
+        }
+        Operation::WriteFloat { address, value } => {
+            let target = offset_address(base, *address)?;
+            ccapi.write_process_memory(&pid, &target, &value.to_be_bytes())?;
+        }
+        Operation::WriteCondensed {
+            address,
+            bytes,
+            count,
+            increment,
+        } => {
+            let start = offset_address(base, *address)?;
+
+            for index in 0..*count as u64 {
+                let target = index
+                    .checked_mul(*increment)
+                    .and_then(|offset| start.checked_add(offset))
+                    .with_context(|| format!("write {index} past '{start:#x}' overflows"))?;
+                ccapi.write_process_memory(&pid, &target, bytes)?;
+            }
+        }
+        Operation::Pointer { address, offset } => {
+            let source = offset_address(base, *address)?;
+            let raw_pointer = ccapi.read_process_memory(&pid, &source, &4)?;
+            let resolved = u32::from_be_bytes(raw_pointer.as_slice().try_into()?) as u64;
+
+            if resolved == 0 {
+                bail!("pointer at '{source:#x}' is null");
+            }
+
+            *pointer = Some(offset_address(resolved, *offset)?);
+        }
+        Operation::FindReplace {
+            start,
+            end,
+            find,
+            replace,
+        } => {
+            let region =
+                MemoryRegion::new(offset_address(base, *start)?, end.saturating_sub(*start));
+            find_replace(ccapi, pid, &region, find, replace)?;
+        }
+        Operation::IfEqual { address, bytes } => {
+            let target = offset_address(base, *address)?;
+            let memory = ccapi.read_process_memory(&pid, &target, &(bytes.len() as u32))?;
+            return Ok(memory == *bytes);
+        }
+        Operation::IfMask { address, mask } => {
+            let target = offset_address(base, *address)?;
+            let memory = ccapi.read_process_memory(&pid, &target, &(mask.len() as u32))?;
+            return Ok(memory
+                .iter()
+                .zip(mask)
+                .all(|(byte, bits)| byte & bits == *bits));
+        }
+        Operation::Copy {
+            source,
+            size,
+            destination,
+        } => {
+            let memory = ccapi.read_process_memory(&pid, &offset_address(base, *source)?, size)?;
+            ccapi.write_process_memory(&pid, &offset_address(base, *destination)?, &memory)?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Adds an address to a resolved pointer, failing instead of wrapping around
+fn offset_address(base: u64, address: u64) -> Result<u64> {
+    match base.checked_add(address) {
+        Some(target) => Ok(target),
+        None => bail!("address '{address:#x}' past pointer '{base:#x}' overflows"),
+    }
+}
+
+/// Replaces every occurrence of `find` within a region, returning the amount replaced
+pub(crate) fn find_replace(
+    ccapi: &CCAPI,
+    pid: u32,
+    region: &MemoryRegion,
+    find: &[u8],
+    replace: &[u8],
+) -> Result<usize> {
+    let mut replaced = 0;
+
+    for (start, memory) in read_mapped(ccapi, pid, region)? {
+        let mut offset = 0;
+
+        while !find.is_empty() && offset + find.len() <= memory.len() {
+            match memory[offset..offset + find.len()] == *find {
+                true => {
+                    let address = start + offset as u64;
+                    ccapi.write_process_memory(&pid, &address, replace)?;
+
+                    offset += find.len();
+                    replaced += 1;
+                }
+                false => offset += 1,
+            }
+        }
+    }
+
+    Ok(replaced)
+}
+
+fn parse_code(block: &[(usize, &str)]) -> Result<Code, CodeParseError> {
+    let name = block[0].1.to_string();
+    let mut lines = block[1..].iter().peekable();
+
+    let flag = lines.peek().map(|(_, line)| *line);
+
+    let constant = match flag {
+        Some("0") | Some("1") => {
+            lines.next();
+            flag == Some("1")
+        }
+        _ => false,
+    };
+
+    let rest: Vec<(usize, &str)> = lines.copied().collect();
+
+    Ok(Code {
+        name,
+        constant,
+        lines: parse_lines(&rest)?,
+    })
+}
+
+/// Parses the numbered code lines of a single code, joining two-line operations
+pub(crate) fn parse_lines(block: &[(usize, &str)]) -> Result<Vec<CodeLine>, CodeParseError> {
+    let mut lines = block.iter();
+    let mut code_lines = Vec::new();
+
+    while let Some((number, line)) = lines.next() {
+        let (code_type, address, value) = split_line(*number, line)?;
+
+        let operation = match code_type {
+            "0" => Operation::Write {
+                address: parse_hex(*number, address)?,
+                bytes: parse_bytes(*number, value)?,
+            },
+            "1" => Operation::WriteText {
+                address: parse_hex(*number, address)?,
+                text: value.to_string(),
+            },
+            "2" => Operation::WriteFloat {
+                address: parse_hex(*number, address)?,
+                value: value
+                    .parse()
+                    .map_err(|_| parse_error(*number, &format!("invalid float '{value}'")))?,
+            },
+            "4" | "B" | "F" => {
+                let (next_number, next_line) = lines.next().ok_or_else(|| {
+                    parse_error(
+                        *number,
+                        &format!("type '{code_type}' requires a second line"),
+                    )
+                })?;
+
+                let (next_type, next_address, next_value) = split_line(*next_number, next_line)?;
+
+                if !next_type.eq_ignore_ascii_case(code_type) {
+                    return Err(parse_error(
+                        *next_number,
+                        &format!("expected a second line of type '{code_type}'"),
+                    ));
+                }
+
+                match code_type {
+                    "4" => Operation::WriteCondensed {
+                        address: parse_hex(*number, address)?,
+                        bytes: parse_bytes(*number, value)?,
+                        count: parse_hex_as(*next_number, next_address)?,
+                        increment: parse_hex(*next_number, next_value)?,
+                    },
+                    "B" => Operation::FindReplace {
+                        start: parse_hex(*number, address)?,
+                        end: parse_hex(*number, value)?,
+                        find: parse_bytes(*next_number, next_address)?,
+                        replace: parse_bytes(*next_number, next_value)?,
+                    },
+                    _ => Operation::Copy {
+                        source: parse_hex(*number, address)?,
+                        size: parse_hex_as(*number, value)?,
+                        destination: parse_hex(*next_number, next_address)?,
+                    },
+                }
+            }
+            "6" => Operation::Pointer {
+                address: parse_hex(*number, address)?,
+                offset: parse_hex(*number, value)?,
+            },
+            "D" => Operation::IfEqual {
+                address: parse_hex(*number, address)?,
+                bytes: parse_bytes(*number, value)?,
+            },
+            "E" => Operation::IfMask {
+                address: parse_hex(*number, address)?,
+                mask: parse_bytes(*number, value)?,
+            },
+            _ => {
+                return Err(parse_error(
+                    *number,
+                    &format!("unknown code type '{code_type}'"),
+                ))
+            }
+        };
+
+        code_lines.push(CodeLine {
+            line: *number,
+            operation,
+        });
+    }
+
+    Ok(code_lines)
+}
+
+/// Splits a code line into its upper-cased type, address and value
+fn split_line(number: usize, line: &str) -> Result<(&'static str, &str, &str), CodeParseError> {
+    let (code_type, rest) = split_word(line);
+    let (address, value) = split_word(rest);
+
+    match (code_type, address, value) {
+        (code_type, address, value) if !address.is_empty() && !value.is_empty() => {
+            let code_type = match code_type.to_ascii_uppercase().as_str() {
+                "0" => "0",
+                "1" => "1",
+                "2" => "2",
+                "4" => "4",
+                "6" => "6",
+                "B" => "B",
+                "D" => "D",
+                "E" => "E",
+                "F" => "F",
+                _ => {
+                    return Err(parse_error(
+                        number,
+                        &format!("unknown code type '{code_type}'"),
+                    ))
+                }
+            };
+
+            Ok((code_type, address, value))
+        }
+        _ => Err(parse_error(number, "expected '<type> <address> <value>'")),
+    }
+}
+
+/// Splits off the first whitespace-separated word, returning it and the trimmed remainder
+pub(crate) fn split_word(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+
+    match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    }
+}
+
+pub(crate) fn parse_hex(number: usize, raw: &str) -> Result<u64, CodeParseError> {
+    u64::from_str_radix(raw.trim_start_matches("0x"), 16)
+        .map_err(|_| parse_error(number, &format!("invalid hex number '{raw}'")))
+}
+
+/// Parses a hex number that must fit in `T`, such as the count of a condensed write
+pub(crate) fn parse_hex_as<T: TryFrom<u64>>(number: usize, raw: &str) -> Result<T, CodeParseError> {
+    T::try_from(parse_hex(number, raw)?)
+        .map_err(|_| parse_error(number, &format!("hex number '{raw}' is out of range")))
+}
+
+pub(crate) fn parse_bytes(number: usize, raw: &str) -> Result<Vec<u8>, CodeParseError> {
+    decode_hex(raw.trim_start_matches("0x"))
+        .map_err(|_| parse_error(number, &format!("invalid hex bytes '{raw}'")))
+}
+
+pub(crate) fn parse_error(line: usize, message: &str) -> CodeParseError {
+    CodeParseError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error_line(source: &str) -> usize {
+        CodeList::parse(source).unwrap_err().line
+    }
+
+    #[test]
+    fn parses_codes_and_flags() {
+        let list = CodeList::parse(
+            "Infinite Health\n1\n0 00010000 0000FFFF\n#\nName\n0\n1 00020000 Player\n#\nFloat\n2 00030000 1.5\n",
+        )
+        .unwrap();
+
+        assert_eq!(list.codes.len(), 3);
+        assert!(list.codes[0].constant);
+        assert!(!list.codes[1].constant);
+        assert!(!list.codes[2].constant);
+
+        assert_eq!(
+            list.codes[0].lines,
+            vec![CodeLine {
+                line: 3,
+                operation: Operation::Write {
+                    address: 0x10000,
+                    bytes: vec![0x00, 0x00, 0xFF, 0xFF],
+                },
+            }]
+        );
+        assert_eq!(
+            list.get("Name").unwrap().lines[0].operation,
+            Operation::WriteText {
+                address: 0x20000,
+                text: "Player".to_string(),
+            }
+        );
+        assert_eq!(
+            list.get("Float").unwrap().lines[0].operation,
+            Operation::WriteFloat {
+                address: 0x30000,
+                value: 1.5,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_line_numbers_across_blank_lines_and_codes() {
+        assert_eq!(parse_error_line("Code\n\n0 00010000 ZZ\n"), 3);
+        assert_eq!(
+            parse_error_line("A\n0 00010000 01\n#\n\nB\n0 00010000\n"),
+            6
+        );
+        assert_eq!(parse_error_line("A\n7 00010000 01\n"), 2);
+        assert_eq!(parse_error_line("A\n2 00010000 fast\n"), 2);
+    }
+
+    #[test]
+    fn parses_second_lines() {
+        let list = CodeList::parse(
+            "Condensed\n4 00010000 01\n4 00000010 00000004\n#\nReplace\nB 00010000 00020000\nb 0A0B 0C0D\n#\nCopy\nF 00010000 00000008\nF 00020000 0\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            list.codes[0].lines,
+            vec![CodeLine {
+                line: 2,
+                operation: Operation::WriteCondensed {
+                    address: 0x10000,
+                    bytes: vec![0x01],
+                    count: 0x10,
+                    increment: 4,
+                },
+            }]
+        );
+        assert_eq!(
+            list.codes[1].lines[0].operation,
+            Operation::FindReplace {
+                start: 0x10000,
+                end: 0x20000,
+                find: vec![0x0A, 0x0B],
+                replace: vec![0x0C, 0x0D],
+            }
+        );
+        assert_eq!(
+            list.codes[2].lines[0].operation,
+            Operation::Copy {
+                source: 0x10000,
+                size: 8,
+                destination: 0x20000,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_missing_or_mismatched_second_lines() {
+        assert_eq!(parse_error_line("A\n4 00010000 01\n"), 2);
+        assert_eq!(parse_error_line("A\n4 00010000 01\n0 00000010 4\n"), 3);
+        assert_eq!(parse_error_line("A\nB 00010000 00020000\nB 0A0B XY\n"), 3);
+        assert_eq!(parse_error_line("A\nF 00010000 100000000\nF 0 0\n"), 2);
+    }
+
+    #[test]
+    fn parses_pointer_chains() {
+        let list = CodeList::parse("Chain\n6 00010000 10\n6 00000004 20\n0 00000008 01\n").unwrap();
+        let operations: Vec<&Operation> = list.codes[0]
+            .lines
+            .iter()
+            .map(|code_line| &code_line.operation)
+            .collect();
+
+        assert_eq!(
+            operations,
+            vec![
+                &Operation::Pointer {
+                    address: 0x10000,
+                    offset: 0x10,
+                },
+                &Operation::Pointer {
+                    address: 4,
+                    offset: 0x20,
+                },
+                &Operation::Write {
+                    address: 8,
+                    bytes: vec![0x01],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_conditionals() {
+        let list = CodeList::parse("If\nD 00010000 0001\ne 00010004 80\n0 00010008 01\n").unwrap();
+
+        assert_eq!(
+            list.codes[0].lines[0].operation,
+            Operation::IfEqual {
+                address: 0x10000,
+                bytes: vec![0x00, 0x01],
+            }
+        );
+        assert_eq!(
+            list.codes[0].lines[1],
+            CodeLine {
+                line: 3,
+                operation: Operation::IfMask {
+                    address: 0x10004,
+                    mask: vec![0x80],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn offset_address_rejects_overflow() {
+        assert_eq!(offset_address(0x10000, 0x10).unwrap(), 0x10010);
+        assert!(offset_address(u64::MAX, 1).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::worker::{StopSignal, Worker};
+use crate::{NotifyIcon, CCAPI};
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Characters that fit on one line of an on-screen notification
+pub const LINE_WIDTH: usize = 40;
+
+/// Lines shown by a single notification
+pub const MAX_LINES: usize = 3;
+
+/// Largest UTF-8 encoded message sent in a single notification
+pub const MAX_MESSAGE_BYTES: usize = 128;
+
+/// How long to wait while the queue is empty before checking it again
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A queued message, counting how many times it was pushed before being shown
+#[derive(Debug, Clone)]
+struct Pending {
+    icon: NotifyIcon,
+    message: String,
+    count: usize,
+}
+
+type PendingQueue = Arc<Mutex<VecDeque<Pending>>>;
+
+/// Shows notifications one at a time on a background thread
+///
+/// Messages are normalized and wrapped with [format_message], and pushing a message
+/// that is already waiting in the queue merges the two into one notification.
+pub struct NotificationQueue {
+    pending: PendingQueue,
+    worker: Worker,
+}
+
+impl NotificationQueue {
+    /// Starts a new empty queue
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to notify
+    /// * `interval` - How long each notification stays on screen before the next one
+    pub fn start(ccapi: &CCAPI, interval: Duration) -> Self {
+        let pending = PendingQueue::default();
+        let thread_pending = Arc::clone(&pending);
+        let ccapi = ccapi.clone();
+
+        let worker =
+            Worker::spawn(move |signal| notify_loop(&ccapi, &thread_pending, interval, signal));
+
+        NotificationQueue { pending, worker }
+    }
+
+    /// Queues a message, merging it with an identical message that has not been shown yet
+    pub fn push(&self, icon: NotifyIcon, message: &str) -> Result<()> {
+        let message = normalize(message);
+
+        if message.trim().is_empty() {
+            bail!("Notification message is empty");
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+
+        match pending
+            .iter_mut()
+            .find(|queued| queued.icon == icon && queued.message == message)
+        {
+            Some(queued) => queued.count += 1,
+            None => pending.push_back(Pending {
+                icon,
+                message,
+                count: 1,
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of notifications waiting to be shown
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.lock().unwrap().is_empty()
+    }
+
+    /// Drops every notification that has not been shown yet
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Returns whether notifications are still being sent
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops sending, returning the error that ended the queue early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+fn notify_loop(
+    ccapi: &CCAPI,
+    pending: &PendingQueue,
+    interval: Duration,
+    signal: &StopSignal,
+) -> Result<()> {
+    loop {
+        let next = pending.lock().unwrap().pop_front();
+
+        let next = match next {
+            Some(next) => next,
+            None => match signal.sleep(IDLE_INTERVAL) {
+                true => continue,
+                false => return Ok(()),
+            },
+        };
+
+        let message = match next.count {
+            1 => next.message,
+            count => format!("{} (x{count})", next.message),
+        };
+
+        for page in format_message(&message) {
+            ccapi.notify(next.icon, &page)?;
+
+            if !signal.sleep(interval) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Splits a message into notifications that fit on screen
+///
+/// Text is normalized to characters the console displays, then word-wrapped to
+/// [LINE_WIDTH] characters. Lines are grouped into notifications of at most [MAX_LINES]
+/// lines and [MAX_MESSAGE_BYTES] bytes, never splitting a UTF-8 character.
+pub fn format_message(message: &str) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut page_lines = 0;
+
+    for line in wrap(&normalize(message)) {
+        let separator = if page.is_empty() { 0 } else { 1 };
+
+        if page_lines == MAX_LINES || page.len() + separator + line.len() > MAX_MESSAGE_BYTES {
+            pages.push(page);
+            page = String::new();
+            page_lines = 0;
+        }
+
+        if !page.is_empty() {
+            page.push('\n');
+        }
+
+        page.push_str(&line);
+        page_lines += 1;
+    }
+
+    if !page.is_empty() {
+        pages.push(page);
+    }
+
+    pages
+}
+
+/// Replaces typographic punctuation with ASCII and drops control characters
+fn normalize(message: &str) -> String {
+    message
+        .replace("\r\n", "\n")
+        .replace('\u{2026}', "...")
+        .chars()
+        .filter_map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201B}' => Some('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201F}' => Some('"'),
+            '\u{2013}' | '\u{2014}' | '\u{2212}' => Some('-'),
+            '\u{00A0}' | '\t' | '\r' => Some(' '),
+            '\n' => Some('\n'),
+            _ if c.is_control() => None,
+            _ => Some(c),
+        })
+        .collect()
+}
+
+/// Word-wraps text to [LINE_WIDTH] characters per line, keeping explicit line breaks
+///
+/// Words longer than a line and lines longer than [MAX_MESSAGE_BYTES] are split at
+/// character boundaries.
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let line_width = line.chars().count();
+            let word_width = word.chars().count();
+
+            if !line.is_empty() && line_width + 1 + word_width <= LINE_WIDTH {
+                line.push(' ');
+                line.push_str(word);
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(line);
+            }
+
+            line = String::new();
+
+            for c in word.chars() {
+                if line.chars().count() == LINE_WIDTH
+                    || line.len() + c.len_utf8() > MAX_MESSAGE_BYTES
+                {
+                    lines.push(line);
+                    line = String::new();
+                }
+
+                line.push(c);
+            }
+        }
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_lines_of_exactly_the_line_width() {
+        let exact = format!("{} {}", "a".repeat(19), "b".repeat(20));
+        assert_eq!(exact.len(), LINE_WIDTH);
+        assert_eq!(wrap(&exact), vec![exact.clone()]);
+
+        let over = format!("{exact}c");
+        assert_eq!(wrap(&over), vec!["a".repeat(19), "b".repeat(20) + "c"]);
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(
+            wrap("Dumping memory of BLUS30001 to dump.bin has finished"),
+            vec!["Dumping memory of BLUS30001 to dump.bin", "has finished"]
+        );
+    }
+
+    #[test]
+    fn splits_words_longer_than_a_line() {
+        let word = "x".repeat(LINE_WIDTH * 2 + 5);
+        assert_eq!(
+            wrap(&format!("ab {word} cd")),
+            vec![
+                "ab".to_string(),
+                "x".repeat(LINE_WIDTH),
+                "x".repeat(LINE_WIDTH),
+                "xxxxx cd".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_line_breaks() {
+        assert_eq!(
+            wrap("one\ntwo  three\n\nfour"),
+            vec!["one", "two three", "four"]
+        );
+    }
+
+    #[test]
+    fn formats_empty_input_as_no_pages() {
+        assert!(format_message("").is_empty());
+        assert!(format_message(" \n\t\n").is_empty());
+    }
+
+    #[test]
+    fn splits_pages_by_line_count_and_size() {
+        assert_eq!(
+            format_message("1\n2\n3\n4"),
+            vec!["1\n2\n3".to_string(), "4".to_string()]
+        );
+
+        // Two full lines of two-byte characters exceed the message size
+        let line = "\u{e9}".repeat(LINE_WIDTH);
+        let pages = format_message(&format!("{line}\n{line}"));
+        assert_eq!(pages, vec![line.clone(), line]);
+        assert!(pages.iter().all(|page| page.len() <= MAX_MESSAGE_BYTES));
+    }
+
+    #[test]
+    fn normalizes_punctuation_and_control_characters() {
+        assert_eq!(
+            format_message("\u{201C}Done\u{201D} \u{2013} it\u{2019}s saved\u{2026}\u{7}"),
+            vec!["\"Done\" - it's saved..."]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::CCAPI;
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Bytes to write at a single address, along with the bytes they replaced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Patch {
+    pub address: u64,
+    #[serde(with = "crate::hex")]
+    pub bytes: Vec<u8>,
+    /// Bytes read from the address right before the patch was written
+    #[serde(
+        default,
+        with = "crate::hex::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub original: Option<Vec<u8>>,
+}
+
+/// Lifecycle of a [PatchSet](crate::patch::PatchSet)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatchState {
+    /// Not written to any process
+    Pending,
+    /// Written to the given process
+    Applied(u32),
+    /// Written to a process that has since exited
+    Stale(u32),
+}
+
+impl Default for PatchState {
+    fn default() -> Self {
+        PatchState::Pending
+    }
+}
+
+/// An ordered list of patches that can be applied and reverted exactly
+///
+/// Patch sets are stored as JSON so they can be shared as files, e.g.
+///
+/// ```json
+/// {
+///   "name": "infinite ammo",
+///   "patches": [{ "address": 1234567, "bytes": "60000000" }]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatchSet {
+    #[serde(default)]
+    pub name: String,
+    pub patches: Vec<Patch>,
+    #[serde(skip)]
+    state: PatchState,
+}
+
+impl PatchSet {
+    /// Returns a new empty patch set
+    pub fn new(name: &str) -> Self {
+        PatchSet {
+            name: name.to_string(),
+            ..PatchSet::default()
+        }
+    }
+
+    /// Adds a patch to the end of the set
+    pub fn patch(mut self, address: u64, bytes: &[u8]) -> Self {
+        self.push(address, bytes);
+        self
+    }
+
+    /// Adds a patch to the end of the set
+    pub fn push(&mut self, address: u64, bytes: &[u8]) {
+        self.patches.push(Patch {
+            address,
+            bytes: bytes.to_vec(),
+            original: None,
+        });
+    }
+
+    /// Returns the current state of the set
+    pub fn state(&self) -> PatchState {
+        self.state
+    }
+
+    /// Writes every patch to a process in order, saving the original bytes first
+    ///
+    /// If any write fails, patches already written are reverted before returning the error.
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `pid` - The process identifier to patch
+    pub fn apply(&mut self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        if let PatchState::Applied(applied_pid) = self.state {
+            bail!(
+                "patch set '{}' is already applied to pid '{applied_pid}'",
+                self.name
+            );
+        }
+
+        for index in 0..self.patches.len() {
+            if let Err(e) = self.apply_patch(ccapi, pid, index) {
+                let undo_result = self.revert_patches(ccapi, pid, index);
+                self.state = PatchState::Pending;
+
+                return match undo_result {
+                    Ok(()) => Err(e),
+                    Err(undo_error) => Err(e.context(format!(
+                        "failed to undo patches already written: {undo_error}"
+                    ))),
+                };
+            }
+        }
+
+        self.state = PatchState::Applied(pid);
+
+        Ok(())
+    }
+
+    /// Restores the original bytes of every patch, in reverse order
+    ///
+    /// The set is marked stale instead if the patched process has exited.
+    pub fn revert(&mut self, ccapi: &CCAPI) -> Result<()> {
+        let pid = match self.state {
+            PatchState::Applied(pid) => pid,
+            PatchState::Stale(pid) => bail!("patched process '{pid}' has exited"),
+            PatchState::Pending => bail!("patch set '{}' is not applied", self.name),
+        };
+
+        if self.refresh(ccapi)? != PatchState::Applied(pid) {
+            bail!("patched process '{pid}' has exited");
+        }
+
+        self.revert_patches(ccapi, pid, self.patches.len())?;
+        self.state = PatchState::Pending;
+
+        Ok(())
+    }
+
+    /// Marks the set stale if the patched process is no longer running
+    pub fn refresh(&mut self, ccapi: &CCAPI) -> Result<PatchState> {
+        if let PatchState::Applied(pid) = self.state {
+            if !ccapi.get_process_list()?.contains(&pid) {
+                self.state = PatchState::Stale(pid);
+            }
+        }
+
+        Ok(self.state)
+    }
+
+    /// Returns whether the patched process has exited
+    pub fn is_stale(&mut self, ccapi: &CCAPI) -> Result<bool> {
+        Ok(matches!(self.refresh(ccapi)?, PatchState::Stale(_)))
+    }
+
+    /// Parses a patch set from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the patch set to JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Loads a patch set from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)?;
+
+        PatchSet::from_json(&json).with_context(|| format!("invalid patch set file {path:?}"))
+    }
+
+    /// Saves the patch set to a JSON file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    fn apply_patch(&mut self, ccapi: &CCAPI, pid: u32, index: usize) -> Result<()> {
+        let patch = &mut self.patches[index];
+        let size = patch.bytes.len() as u32;
+
+        let original = ccapi.read_process_memory(&pid, &patch.address, &size)?;
+        patch.original = Some(original);
+
+        ccapi.write_process_memory(&pid, &patch.address, &patch.bytes)
+    }
+
+    /// Reverts the first `count` patches, starting from the last one
+    fn revert_patches(&self, ccapi: &CCAPI, pid: u32, count: usize) -> Result<()> {
+        for patch in self.patches[..count].iter().rev() {
+            let original = patch
+                .original
+                .as_ref()
+                .context("original bytes were never saved")?;
+
+            ensure!(
+                original.len() == patch.bytes.len(),
+                "original bytes at '{:#x}' do not match the patch size",
+                patch.address
+            );
+
+            ccapi.write_process_memory(&pid, &patch.address, original)?;
+        }
+
+        Ok(())
+    }
+}
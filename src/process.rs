@@ -0,0 +1,118 @@
+//! Typed information about console processes
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Processes launched from the internal flash are part of the system software
+const SYSTEM_PREFIX: &str = "/dev_flash/";
+
+/// How often [CCAPI::wait_for_process](crate::CCAPI::wait_for_process) polls the process list
+pub(crate) const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A running process and what can be told about it from its path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Process {
+    pub pid: u32,
+    /// Full executable path, such as `/dev_hdd0/game/BLUS30001/USRDIR/EBOOT.BIN`
+    pub path: String,
+    /// Last component of the path, such as `EBOOT.BIN`
+    pub name: String,
+    pub title_id: Option<String>,
+    /// True for system software such as the VSH (XMB)
+    pub is_system: bool,
+}
+
+impl Process {
+    /// Builds a process from its identifier and the path returned by the console
+    pub fn new(pid: u32, path: &str) -> Self {
+        Process {
+            pid,
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            title_id: title_id_from_path(path),
+            is_system: path.starts_with(SYSTEM_PREFIX),
+        }
+    }
+}
+
+/// Names of running processes, as built by [CCAPI::get_process_map](crate::CCAPI::get_process_map)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessMap {
+    pub names: HashMap<u32, String>,
+    /// Pids whose name could not be looked up, usually because they exited meanwhile
+    pub failed: Vec<u32>,
+}
+
+/// Extracts a title ID (e.g. `BLUS30001`) from a process path
+///
+/// Matches the first path component made of four uppercase letters followed by five digits,
+/// such as in `/dev_hdd0/game/BLUS30001/USRDIR/EBOOT.BIN`.
+pub fn title_id_from_path(path: &str) -> Option<String> {
+    path.split('/')
+        .find(|component| is_title_id(component))
+        .map(String::from)
+}
+
+fn is_title_id(value: &str) -> bool {
+    let bytes = value.as_bytes();
+
+    bytes.len() == 9
+        && bytes[..4].iter().all(u8::is_ascii_uppercase)
+        && bytes[4..].iter().all(u8::is_ascii_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_title_ids_from_game_paths() {
+        assert_eq!(
+            title_id_from_path("/dev_hdd0/game/BLUS12345/USRDIR/EBOOT.BIN"),
+            Some("BLUS12345".to_string())
+        );
+        assert_eq!(
+            title_id_from_path("/dev_usb000/NPEB01234/USRDIR/EBOOT.BIN"),
+            Some("NPEB01234".to_string())
+        );
+    }
+
+    #[test]
+    fn finds_no_title_id_in_other_paths() {
+        // Disc games are launched from a fixed path without their title ID
+        assert_eq!(
+            title_id_from_path("/dev_bdvd/PS3_GAME/USRDIR/EBOOT.BIN"),
+            None
+        );
+        assert_eq!(title_id_from_path("/dev_flash/vsh/module/vsh.self"), None);
+        assert_eq!(
+            title_id_from_path("/dev_hdd0/game/blus12345/EBOOT.BIN"),
+            None
+        );
+        assert_eq!(
+            title_id_from_path("/dev_hdd0/game/BLUS1234/EBOOT.BIN"),
+            None
+        );
+        assert_eq!(
+            title_id_from_path("/dev_hdd0/game/BLUS123456/EBOOT.BIN"),
+            None
+        );
+        assert_eq!(title_id_from_path(""), None);
+    }
+
+    #[test]
+    fn describes_processes_from_their_path() {
+        let game = Process::new(0x1010200, "/dev_hdd0/game/BLUS12345/USRDIR/EBOOT.BIN");
+        assert_eq!(game.name, "EBOOT.BIN");
+        assert_eq!(game.title_id, Some("BLUS12345".to_string()));
+        assert!(!game.is_system);
+
+        let vsh = Process::new(0x1000300, "/dev_flash/vsh/module/vsh.self");
+        assert_eq!(vsh.name, "vsh.self");
+        assert_eq!(vsh.title_id, None);
+        assert!(vsh.is_system);
+
+        assert_eq!(Process::new(1, "EBOOT.BIN").name, "EBOOT.BIN");
+    }
+}
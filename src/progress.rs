@@ -0,0 +1,133 @@
+//! Reporting progress of long-running operations on the console screen
+
+use crate::{NotifyIcon, CCAPI};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shows progress of an operation as [NotifyIcon::Progress] notifications
+///
+/// Updates are throttled so the screen is not flooded, except for the final one
+/// which is always shown. [callback](ProgressReporter::callback) plugs into the
+/// `FnMut(u64, u64)` progress callbacks of chunked operations such as
+/// [Snapshot::capture_with_progress](crate::snapshot::Snapshot::capture_with_progress).
+///
+/// ```no_run
+/// use ccapi::progress::ProgressReporter;
+/// use ccapi::snapshot::Snapshot;
+/// use ccapi::{MemoryRegion, CCAPI};
+/// use std::net::Ipv4Addr;
+///
+/// let ccapi = CCAPI::new(Ipv4Addr::new(192, 168, 1, 100));
+/// let regions = [MemoryRegion::new(0x10000, 0x100000)];
+///
+/// let mut reporter = ProgressReporter::new(&ccapi, "Dumping").terminal(true);
+/// let snapshot = Snapshot::capture_with_progress(&ccapi, 1, &regions, reporter.callback());
+/// ```
+pub struct ProgressReporter {
+    ccapi: CCAPI,
+    label: String,
+    interval: Duration,
+    terminal: bool,
+    started: Instant,
+    last_update: Option<Instant>,
+}
+
+impl ProgressReporter {
+    /// Creates a reporter, measuring the ETA from now
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to show progress on
+    /// * `label` - What is being done, such as `Dumping`
+    pub fn new(ccapi: &CCAPI, label: &str) -> Self {
+        ProgressReporter {
+            ccapi: ccapi.clone(),
+            label: label.to_string(),
+            interval: DEFAULT_UPDATE_INTERVAL,
+            terminal: false,
+            started: Instant::now(),
+            last_update: None,
+        }
+    }
+
+    /// Sets the minimum time between two notifications
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets whether every update is also printed to stderr
+    pub fn terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    /// Reports that `done` out of `total` units are complete
+    ///
+    /// A notification is only sent if the update interval has passed since the
+    /// previous one, or if the operation is complete.
+    pub fn update(&mut self, done: u64, total: u64) -> Result<()> {
+        let message = format_progress(&self.label, done, total, self.started.elapsed());
+        let complete = done >= total;
+
+        if self.terminal {
+            eprint!("\r{message}    ");
+
+            if complete {
+                eprintln!();
+            }
+        }
+
+        let due = match self.last_update {
+            Some(last_update) => last_update.elapsed() >= self.interval,
+            None => true,
+        };
+
+        if due || complete {
+            self.last_update = Some(Instant::now());
+            self.ccapi.notify(NotifyIcon::Progress, &message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a progress callback that calls [update](ProgressReporter::update)
+    ///
+    /// Notifications that fail to send are skipped, so they never abort the operation.
+    pub fn callback(&mut self) -> impl FnMut(u64, u64) + '_ {
+        move |done, total| {
+            let _ = self.update(done, total);
+        }
+    }
+}
+
+/// Formats a progress line such as `Dumping: 42% (ETA 1m 05s)`
+pub fn format_progress(label: &str, done: u64, total: u64, elapsed: Duration) -> String {
+    if total == 0 || done >= total {
+        return format!("{label}: 100% (done in {})", format_duration(elapsed));
+    }
+
+    let percent = done * 100 / total;
+
+    match done {
+        0 => format!("{label}: {percent}%"),
+        _ => {
+            let remaining = elapsed.as_secs_f64() * (total - done) as f64 / done as f64;
+            let eta = format_duration(Duration::from_secs_f64(remaining));
+
+            format!("{label}: {percent}% (ETA {eta})")
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
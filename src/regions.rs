@@ -0,0 +1,275 @@
+use crate::snapshot::chunks;
+use crate::{ConsoleError, MemoryRegion, CCAPI};
+use anyhow::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::slice;
+use std::sync::Mutex;
+
+/// Default probing granularity, matching the 64KB pages used by PS3 processes
+pub const DEFAULT_PAGE_SIZE: u64 = 0x10000;
+
+const ADDRESS_SPACE_END: u64 = 0x1_0000_0000;
+
+/// Discovers the mapped memory regions of a process by reading a byte from every page
+///
+/// Reads failing with [ConsoleError::EFAULT](crate::ConsoleError::EFAULT) mark a page
+/// as unmapped. The result of every probed page is cached per pid until invalidated,
+/// so later lookups only probe pages that were not seen before.
+#[derive(Debug)]
+pub struct RegionProber {
+    page_size: u64,
+    start: u64,
+    end: u64,
+    cache: Mutex<HashMap<u32, BTreeMap<u64, bool>>>,
+}
+
+impl Default for RegionProber {
+    fn default() -> Self {
+        RegionProber {
+            page_size: DEFAULT_PAGE_SIZE,
+            start: 0,
+            end: ADDRESS_SPACE_END,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RegionProber {
+    /// Returns a prober covering the full 32-bit address space
+    pub fn new() -> Self {
+        RegionProber::default()
+    }
+
+    /// Sets the probing granularity
+    pub fn page_size(mut self, page_size: u64) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Limits probing to the addresses between `start` and `end`
+    pub fn range(mut self, start: u64, end: u64) -> Self {
+        self.start = start;
+        self.end = end.min(ADDRESS_SPACE_END);
+        self
+    }
+
+    /// Returns the mapped regions of a process, probing only pages that are not cached
+    pub fn regions(&self, ccapi: &CCAPI, pid: u32) -> Result<Vec<MemoryRegion>> {
+        self.regions_with_progress(ccapi, pid, |_, _| {})
+    }
+
+    /// Same as [regions](crate::regions::RegionProber::regions), calling `progress`
+    /// with the amount of pages looked up so far and the total after each page
+    pub fn regions_with_progress<F>(
+        &self,
+        ccapi: &CCAPI,
+        pid: u32,
+        progress: F,
+    ) -> Result<Vec<MemoryRegion>>
+    where
+        F: FnMut(u64, u64),
+    {
+        self.probe_pages(ccapi, pid, self.start, self.end, false, progress)
+    }
+
+    /// Probes the process again, replacing any cached pages
+    pub fn probe(&self, ccapi: &CCAPI, pid: u32) -> Result<Vec<MemoryRegion>> {
+        self.probe_with_progress(ccapi, pid, |_, _| {})
+    }
+
+    /// Same as [probe](crate::regions::RegionProber::probe), calling `progress`
+    /// with the amount of pages probed so far and the total after each page
+    pub fn probe_with_progress<F>(
+        &self,
+        ccapi: &CCAPI,
+        pid: u32,
+        progress: F,
+    ) -> Result<Vec<MemoryRegion>>
+    where
+        F: FnMut(u64, u64),
+    {
+        self.probe_pages(ccapi, pid, self.start, self.end, true, progress)
+    }
+
+    /// Returns the cached regions of a process, if every page in range was probed before
+    pub fn cached(&self, pid: u32) -> Option<Vec<MemoryRegion>> {
+        let cache = self.cache.lock().unwrap();
+        let pages = cache.get(&pid)?;
+        let mut regions = Vec::new();
+
+        for page in self.pages(self.start, self.end) {
+            let mapped = *pages.get(&page)?;
+            self.push_page(&mut regions, page, mapped, self.end);
+        }
+
+        Some(regions)
+    }
+
+    /// Forgets the cached pages of a process
+    pub fn invalidate(&self, pid: u32) {
+        self.cache.lock().unwrap().remove(&pid);
+    }
+
+    /// Forgets the cached pages of every process
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Returns the parts of the requested regions that are mapped in the process
+    ///
+    /// Only the pages covered by the requested regions are probed, if not cached already.
+    pub fn clip(
+        &self,
+        ccapi: &CCAPI,
+        pid: u32,
+        requested: &[MemoryRegion],
+    ) -> Result<Vec<MemoryRegion>> {
+        let mut mapped = Vec::new();
+
+        for request in requested {
+            let start = request.address.max(self.start);
+            let end = request.end().min(self.end);
+
+            if start < end {
+                mapped.extend(self.probe_pages(ccapi, pid, start, end, false, |_, _| {})?);
+            }
+        }
+
+        Ok(clip_regions(requested, &mapped))
+    }
+
+    /// Looks up every page between `start` and `end`, probing those that are not cached
+    /// (or all of them if `force` is set), and returns the mapped ones as regions
+    fn probe_pages<F>(
+        &self,
+        ccapi: &CCAPI,
+        pid: u32,
+        start: u64,
+        end: u64,
+        force: bool,
+        mut progress: F,
+    ) -> Result<Vec<MemoryRegion>>
+    where
+        F: FnMut(u64, u64),
+    {
+        let pages: Vec<u64> = self.pages(start, end).collect();
+        let total = pages.len() as u64;
+        let mut regions: Vec<MemoryRegion> = Vec::new();
+
+        for (index, page) in pages.into_iter().enumerate() {
+            let cached = match force {
+                true => None,
+                false => self
+                    .cache
+                    .lock()
+                    .unwrap()
+                    .get(&pid)
+                    .and_then(|pages| pages.get(&page).copied()),
+            };
+
+            let mapped = match cached {
+                Some(mapped) => mapped,
+                None => {
+                    let mapped = match ccapi.read_process_memory(&pid, &page, &1) {
+                        Ok(_) => true,
+                        Err(e) if is_unmapped(&e) => false,
+                        Err(e) => return Err(e),
+                    };
+
+                    self.cache
+                        .lock()
+                        .unwrap()
+                        .entry(pid)
+                        .or_default()
+                        .insert(page, mapped);
+
+                    mapped
+                }
+            };
+
+            self.push_page(&mut regions, page, mapped, end);
+            progress(index as u64 + 1, total);
+        }
+
+        Ok(regions)
+    }
+
+    /// Returns the page addresses covering `start` up to `end`
+    fn pages(&self, start: u64, end: u64) -> impl Iterator<Item = u64> {
+        let first = start - start % self.page_size;
+        (first..end).step_by(self.page_size as usize)
+    }
+
+    /// Adds a mapped page to a list of regions, extending the last region if contiguous
+    fn push_page(&self, regions: &mut Vec<MemoryRegion>, page: u64, mapped: bool, end: u64) {
+        if !mapped {
+            return;
+        }
+
+        let page_size = self.page_size.min(end - page);
+
+        match regions.last_mut() {
+            Some(last) if last.end() == page => last.size += page_size,
+            _ => regions.push(MemoryRegion::new(page, page_size)),
+        }
+    }
+}
+
+/// Returns the mapped parts of a region, using the prober shared by the client
+///
+/// See [CCAPI::region_prober](crate::CCAPI::region_prober).
+pub fn mapped_parts(ccapi: &CCAPI, pid: u32, region: &MemoryRegion) -> Result<Vec<MemoryRegion>> {
+    ccapi
+        .region_prober()
+        .clip(ccapi, pid, slice::from_ref(region))
+}
+
+/// Reads the mapped parts of a region, returning each contiguous run with its address
+///
+/// Unmapped pages are skipped instead of failing the whole read, so scans over a
+/// region only touch valid memory.
+pub(crate) fn read_mapped(
+    ccapi: &CCAPI,
+    pid: u32,
+    region: &MemoryRegion,
+) -> Result<Vec<(u64, Vec<u8>)>> {
+    let mut runs = Vec::new();
+
+    for part in mapped_parts(ccapi, pid, region)? {
+        let mut memory = Vec::with_capacity(part.size as usize);
+
+        for (address, size) in chunks(&part) {
+            memory.extend(ccapi.read_process_memory(&pid, &address, &size)?);
+        }
+
+        runs.push((part.address, memory));
+    }
+
+    Ok(runs)
+}
+
+/// Returns whether an error was caused by accessing unmapped memory
+pub fn is_unmapped(error: &Error) -> bool {
+    matches!(
+        error.downcast_ref::<ConsoleError>(),
+        Some(ConsoleError::EFAULT)
+    )
+}
+
+/// Returns the intersections of the requested regions with the mapped ones
+pub fn clip_regions(requested: &[MemoryRegion], mapped: &[MemoryRegion]) -> Vec<MemoryRegion> {
+    let mut clipped = Vec::new();
+
+    for request in requested {
+        for region in mapped {
+            let start = request.address.max(region.address);
+            let end = request.end().min(region.end());
+
+            if start < end {
+                clipped.push(MemoryRegion::new(start, end - start));
+            }
+        }
+    }
+
+    clipped
+}
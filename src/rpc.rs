@@ -0,0 +1,367 @@
+//! Calling functions inside a game process
+//!
+//! [`Rpc::install`] hooks a function the game calls regularly (for example once per frame)
+//! with a stub that checks a parameter block in the code cave. A call writes the target
+//! function and its arguments into the block and raises a flag; the next time the hooked
+//! function runs, the stub performs the call on the game thread, stores `r3` and clears
+//! the flag again.
+//!
+//! Arguments follow the PPU calling convention: integers and pointers are passed in
+//! `r3`-`r10`, floating point values in `f1`-`f8`, and every argument takes up a general
+//! purpose register slot whatever its type.
+
+use crate::assembler::{self, INSTRUCTION_SIZE};
+use crate::hook::{CodeCave, Hook};
+use crate::{MemoryRegion, CCAPI};
+use anyhow::{bail, Result};
+use std::convert::TryInto;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Most arguments a single call can take
+pub const MAX_ARGUMENTS: usize = 8;
+
+/// Bytes available in the parameter block for string arguments, including terminators
+pub const STRING_AREA_SIZE: usize = 0x200;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Space the hook needs after the stub for the relocated instruction and return branch
+const TRAMPOLINE_SIZE: u64 = 4 * INSTRUCTION_SIZE;
+
+// Parameter block layout
+const FLAG_OFFSET: u64 = 0x00;
+const FUNCTION_OFFSET: u64 = 0x04;
+const TOC_OFFSET: u64 = 0x08;
+const GPR_OFFSET: u64 = 0x10;
+const FPR_OFFSET: u64 = 0x50;
+const RESULT_OFFSET: u64 = 0x90;
+const STRING_OFFSET: u64 = 0xA0;
+const BLOCK_SIZE: u64 = STRING_OFFSET + STRING_AREA_SIZE as u64;
+
+const FLAG_IDLE: u32 = 0;
+const FLAG_PENDING: u32 = 1;
+const FLAG_DONE: u32 = 2;
+
+/// A single argument to a remote call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    /// Passed in the next general purpose register
+    Int(u64),
+    /// Passed in the next floating point register
+    Float(f64),
+    /// Copied into the parameter block as a null terminated string, passed as a pointer
+    String(String),
+}
+
+macro_rules! impl_int_argument {
+    ($($type:ty),*) => {
+        $(
+            impl From<$type> for Argument {
+                fn from(value: $type) -> Self {
+                    Argument::Int(value as u64)
+                }
+            }
+        )*
+    };
+}
+
+impl_int_argument!(u8, u16, u32, u64, i8, i16, i32, i64, bool);
+
+impl From<f32> for Argument {
+    fn from(value: f32) -> Self {
+        Argument::Float(value as f64)
+    }
+}
+
+impl From<f64> for Argument {
+    fn from(value: f64) -> Self {
+        Argument::Float(value)
+    }
+}
+
+impl From<&str> for Argument {
+    fn from(value: &str) -> Self {
+        Argument::String(value.to_string())
+    }
+}
+
+impl From<String> for Argument {
+    fn from(value: String) -> Self {
+        Argument::String(value)
+    }
+}
+
+/// An RPC stub installed in a process
+///
+/// The stub is removed, and the memory it used restored, when dropped or with
+/// [`Rpc::uninstall`].
+#[derive(Debug)]
+pub struct Rpc {
+    ccapi: CCAPI,
+    pid: u32,
+    hook: Hook,
+    block: u64,
+    original_block: Vec<u8>,
+    timeout: Duration,
+    installed: bool,
+}
+
+impl Rpc {
+    /// Installs the RPC stub into a process
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `pid` - The process to call functions in
+    /// * `hook_target` - A function the game calls regularly, which runs pending calls
+    /// * `cave` - Unused memory for the stub and parameter block
+    pub fn install(ccapi: &CCAPI, pid: u32, hook_target: u64, cave: CodeCave) -> Result<Self> {
+        // The stub has the same length wherever it is assembled
+        let stub_size = assemble_stub(0)?.len() as u64;
+        let cave_size = stub_size + TRAMPOLINE_SIZE + 8 + BLOCK_SIZE;
+
+        let cave = cave.resolve(ccapi, pid, cave_size as u32)?;
+
+        let block = (cave + stub_size + TRAMPOLINE_SIZE + 7) & !7;
+        let original_block = ccapi.read_process_memory(&pid, &block, &(BLOCK_SIZE as u32))?;
+
+        ccapi.write_process_memory(&pid, &block, &vec![0; BLOCK_SIZE as usize])?;
+
+        let hook = match Hook::install_at(ccapi, pid, hook_target, cave, &assemble_stub(block)?) {
+            Ok(hook) => hook,
+            Err(error) => {
+                let _ = ccapi.write_process_memory(&pid, &block, &original_block);
+                return Err(error);
+            }
+        };
+
+        Ok(Rpc {
+            ccapi: ccapi.clone(),
+            pid,
+            hook,
+            block,
+            original_block,
+            timeout: DEFAULT_TIMEOUT,
+            installed: true,
+        })
+    }
+
+    /// Sets how long [`Rpc::call`] waits for the game to run a call
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Address of the parameter block
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+
+    /// Calls the function at `function` with the process' current TOC and returns `r3`
+    pub fn call(&self, function: u64, arguments: &[Argument]) -> Result<u64> {
+        self.call_with_toc(function, 0, arguments)
+    }
+
+    /// Calls a function through its official procedure descriptor (OPD) and returns `r3`
+    ///
+    /// Function pointers in PS3 executables point to descriptors holding the code
+    /// address and the TOC the function expects.
+    pub fn call_opd(&self, descriptor: u64, arguments: &[Argument]) -> Result<u64> {
+        let opd = self.ccapi.read_process_memory(&self.pid, &descriptor, &8)?;
+
+        let function = u32::from_be_bytes(opd[0..4].try_into()?) as u64;
+        let toc = u32::from_be_bytes(opd[4..8].try_into()?) as u64;
+
+        self.call_with_toc(function, toc, arguments)
+    }
+
+    fn call_with_toc(&self, function: u64, toc: u64, arguments: &[Argument]) -> Result<u64> {
+        if !self.installed {
+            bail!("RPC stub has been uninstalled");
+        }
+
+        if arguments.len() > MAX_ARGUMENTS {
+            bail!(
+                "{} arguments given, but at most {MAX_ARGUMENTS} are supported",
+                arguments.len()
+            );
+        }
+
+        if self.read_u32(FLAG_OFFSET)? == FLAG_PENDING {
+            bail!("a previous RPC call is still pending");
+        }
+
+        let mut gprs = [0u64; MAX_ARGUMENTS];
+        let mut fprs = [0f64; MAX_ARGUMENTS];
+        let mut floats = 0;
+        let mut strings = Vec::new();
+
+        for (slot, argument) in arguments.iter().enumerate() {
+            match argument {
+                Argument::Int(value) => gprs[slot] = *value,
+                Argument::Float(value) => {
+                    fprs[floats] = *value;
+                    floats += 1;
+                }
+                Argument::String(value) => {
+                    if value.contains('\0') {
+                        bail!("string argument {slot} contains a null byte");
+                    }
+
+                    gprs[slot] = self.block + STRING_OFFSET + strings.len() as u64;
+                    strings.extend(value.as_bytes());
+                    strings.push(0);
+                }
+            }
+        }
+
+        if strings.len() > STRING_AREA_SIZE {
+            bail!(
+                "string arguments take {} bytes, but only {STRING_AREA_SIZE} are available",
+                strings.len()
+            );
+        }
+
+        let mut parameters = Vec::with_capacity((STRING_OFFSET - FUNCTION_OFFSET) as usize);
+        parameters.extend(&(function as u32).to_be_bytes());
+        parameters.extend(&(toc as u32).to_be_bytes());
+        parameters.extend(&[0; 4]);
+        gprs.iter()
+            .for_each(|gpr| parameters.extend(&gpr.to_be_bytes()));
+        fprs.iter()
+            .for_each(|fpr| parameters.extend(&fpr.to_bits().to_be_bytes()));
+        parameters.extend(&[0; (STRING_OFFSET - RESULT_OFFSET) as usize]);
+        parameters.extend(strings);
+
+        // Raise the flag last so the stub never sees a partial call
+        self.write(FUNCTION_OFFSET, &parameters)?;
+        self.write(FLAG_OFFSET, &FLAG_PENDING.to_be_bytes())?;
+
+        let start = Instant::now();
+
+        loop {
+            if self.read_u32(FLAG_OFFSET)? == FLAG_DONE {
+                break;
+            }
+
+            if start.elapsed() >= self.timeout {
+                self.write(FLAG_OFFSET, &FLAG_IDLE.to_be_bytes())?;
+                bail!(
+                    "RPC call to '{function:#x}' did not complete within {:?}",
+                    self.timeout
+                );
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let result = self.read(RESULT_OFFSET, 8)?;
+        self.write(FLAG_OFFSET, &FLAG_IDLE.to_be_bytes())?;
+
+        Ok(u64::from_be_bytes(result.as_slice().try_into()?))
+    }
+
+    /// Removes the stub and restores the memory it used
+    pub fn uninstall(&mut self) -> Result<()> {
+        if !self.installed {
+            return Ok(());
+        }
+
+        self.hook.unhook()?;
+        self.ccapi
+            .write_process_memory(&self.pid, &self.block, &self.original_block)?;
+
+        self.installed = false;
+
+        Ok(())
+    }
+
+    fn read(&self, offset: u64, size: u32) -> Result<Vec<u8>> {
+        let address = self.block + offset;
+
+        // The stub writes behind the cache's back
+        self.ccapi
+            .invalidate(&self.pid, MemoryRegion::new(address, size as u64));
+        self.ccapi.read_process_memory(&self.pid, &address, &size)
+    }
+
+    fn read_u32(&self, offset: u64) -> Result<u32> {
+        let bytes = self.read(offset, 4)?;
+        Ok(u32::from_be_bytes(bytes.as_slice().try_into()?))
+    }
+
+    fn write(&self, offset: u64, bytes: &[u8]) -> Result<()> {
+        self.ccapi
+            .write_process_memory(&self.pid, &(self.block + offset), bytes)
+    }
+}
+
+impl Drop for Rpc {
+    fn drop(&mut self) {
+        let _ = self.uninstall();
+    }
+}
+
+/// Assembles the stub that runs pending calls from the parameter block at `block`
+///
+/// It runs on entry to the hooked function, so every argument register is saved around
+/// the call and only the scratch registers `r0`, `r11` and `r12` are clobbered.
+fn assemble_stub(block: u64) -> Result<Vec<u8>> {
+    let gpr_offset = |index: u64| GPR_OFFSET + index * 8;
+    let fpr_offset = |index: u64| FPR_OFFSET + index * 8;
+
+    let mut source = vec![
+        format!("lis r11, {block:#x}@ha"),
+        format!("addi r11, r11, {block:#x}@l"),
+        format!("lwz r12, {FLAG_OFFSET:#x}(r11)"),
+        format!("cmpwi r12, {FLAG_PENDING}"),
+        "bne done".to_string(),
+        "mflr r0".to_string(),
+        "stwu r1, -0x100(r1)".to_string(),
+        "std r0, 0x70(r1)".to_string(),
+        "std r2, 0x78(r1)".to_string(),
+    ];
+
+    // Frame layout above the ABI header and parameter save area: LR, TOC, r3-r10, f1-f8
+    for index in 0..8 {
+        source.push(format!("std r{}, {:#x}(r1)", index + 3, 0x80 + index * 8));
+        source.push(format!("stfd f{}, {:#x}(r1)", index + 1, 0xC0 + index * 8));
+    }
+
+    for index in 0..8 {
+        source.push(format!("ld r{}, {:#x}(r11)", index + 3, gpr_offset(index)));
+        source.push(format!("lfd f{}, {:#x}(r11)", index + 1, fpr_offset(index)));
+    }
+
+    source.extend(vec![
+        format!("lwz r12, {FUNCTION_OFFSET:#x}(r11)"),
+        "mtctr r12".to_string(),
+        format!("lwz r12, {TOC_OFFSET:#x}(r11)"),
+        "cmpwi r12, 0".to_string(),
+        "beq call".to_string(),
+        "mr r2, r12".to_string(),
+        "call:".to_string(),
+        "bctrl".to_string(),
+        format!("lis r11, {block:#x}@ha"),
+        format!("addi r11, r11, {block:#x}@l"),
+        format!("std r3, {RESULT_OFFSET:#x}(r11)"),
+        format!("li r12, {FLAG_DONE}"),
+        format!("stw r12, {FLAG_OFFSET:#x}(r11)"),
+    ]);
+
+    for index in 0..8 {
+        source.push(format!("ld r{}, {:#x}(r1)", index + 3, 0x80 + index * 8));
+        source.push(format!("lfd f{}, {:#x}(r1)", index + 1, 0xC0 + index * 8));
+    }
+
+    source.extend(vec![
+        "ld r2, 0x78(r1)".to_string(),
+        "ld r0, 0x70(r1)".to_string(),
+        "mtlr r0".to_string(),
+        "addi r1, r1, 0x100".to_string(),
+        "done:".to_string(),
+    ]);
+
+    Ok(assembler::assemble(&source.join("\n"), 0)?)
+}
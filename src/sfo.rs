@@ -0,0 +1,266 @@
+//! Parsing `PARAM.SFO` title metadata files
+
+use anyhow::{bail, ensure, Context, Result};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+const SFO_MAGIC: &[u8] = b"\0PSF";
+const HEADER_SIZE: usize = 0x14;
+const INDEX_ENTRY_SIZE: usize = 0x10;
+
+const FORMAT_BINARY: u16 = 0x0004;
+const FORMAT_STRING: u16 = 0x0204;
+const FORMAT_INTEGER: u16 = 0x0404;
+
+/// A single value stored in a `PARAM.SFO`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SfoValue {
+    String(String),
+    Integer(u32),
+    /// Raw data stored without a terminator, such as `ACCOUNT_ID`
+    Binary(Vec<u8>),
+}
+
+/// The keys and values of a `PARAM.SFO` file
+#[derive(Debug, Clone, Default)]
+pub struct ParamSfo {
+    pub entries: BTreeMap<String, SfoValue>,
+}
+
+impl ParamSfo {
+    /// Parses the contents of a `PARAM.SFO` file
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.len() >= HEADER_SIZE && data.starts_with(SFO_MAGIC),
+            "not a PARAM.SFO file"
+        );
+
+        let key_table = read_u32(data, 0x08)? as usize;
+        let data_table = read_u32(data, 0x0C)? as usize;
+        let count = read_u32(data, 0x10)? as usize;
+
+        let mut entries = BTreeMap::new();
+
+        for index in 0..count {
+            let entry = HEADER_SIZE + index * INDEX_ENTRY_SIZE;
+
+            let key_offset = read_u16(data, entry)? as usize;
+            let format = read_u16(data, entry + 0x02)?;
+            let length = read_u32(data, entry + 0x04)? as usize;
+            let value_offset = data_table + read_u32(data, entry + 0x0C)? as usize;
+
+            let key = read_string(data, key_table + key_offset)?;
+            let raw_value = match data.get(value_offset..value_offset + length) {
+                Some(raw_value) => raw_value,
+                None => bail!("value of '{key}' is past the end of the file"),
+            };
+
+            let value = match format {
+                FORMAT_STRING => {
+                    let end = raw_value
+                        .iter()
+                        .position(|byte| *byte == 0)
+                        .unwrap_or(length);
+                    SfoValue::String(String::from_utf8_lossy(&raw_value[..end]).into_owned())
+                }
+                FORMAT_INTEGER => SfoValue::Integer(read_u32(raw_value, 0)?),
+                FORMAT_BINARY => SfoValue::Binary(raw_value.to_vec()),
+                _ => bail!("value of '{key}' has unknown format '{format:#06x}'"),
+            };
+
+            entries.insert(key, value);
+        }
+
+        Ok(ParamSfo { entries })
+    }
+
+    /// Reads and parses a `PARAM.SFO` file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            fs::read(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+
+        ParamSfo::parse(&data).with_context(|| format!("Failed to parse '{}'", path.display()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SfoValue> {
+        self.entries.get(key)
+    }
+
+    /// Returns a string value, or `None` if the key is missing or not a string
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.entries.get(key) {
+            Some(SfoValue::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns an integer value, or `None` if the key is missing or not an integer
+    pub fn get_integer(&self, key: &str) -> Option<u32> {
+        match self.entries.get(key) {
+            Some(SfoValue::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The title ID, such as `BLUS30001`
+    pub fn title_id(&self) -> Option<&str> {
+        self.get_str("TITLE_ID")
+    }
+
+    /// The version of the installed game or update, such as `01.02`
+    pub fn app_version(&self) -> Option<&str> {
+        self.get_str("APP_VER")
+    }
+
+    /// The display name of the title
+    pub fn title(&self) -> Option<&str> {
+        self.get_str("TITLE")
+    }
+
+    /// The content category, such as `DG` (disc game) or `HG` (HDD game)
+    pub fn category(&self) -> Option<&str> {
+        self.get_str("CATEGORY")
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_le_bytes(bytes.try_into()?)),
+        None => bail!("offset '{offset:#x}' is past the end of the file"),
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes(bytes.try_into()?)),
+        None => bail!("offset '{offset:#x}' is past the end of the file"),
+    }
+}
+
+fn read_string(data: &[u8], offset: usize) -> Result<String> {
+    let bytes = match data.get(offset..) {
+        Some(bytes) => bytes,
+        None => bail!("offset '{offset:#x}' is past the end of the file"),
+    };
+
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a `PARAM.SFO` with the given keys, formats and raw values
+    fn build(entries: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut index: Vec<u8> = Vec::new();
+        let mut keys: Vec<u8> = Vec::new();
+        let mut values: Vec<u8> = Vec::new();
+
+        for (key, format, value) in entries {
+            index.extend(&(keys.len() as u16).to_le_bytes());
+            index.extend(&format.to_le_bytes());
+            index.extend(&(value.len() as u32).to_le_bytes());
+            index.extend(&(value.len() as u32).to_le_bytes());
+            index.extend(&(values.len() as u32).to_le_bytes());
+
+            keys.extend(key.as_bytes());
+            keys.push(0);
+            values.extend(*value);
+        }
+
+        while keys.len() % 4 != 0 {
+            keys.push(0);
+        }
+
+        let key_table = HEADER_SIZE + index.len();
+        let data_table = key_table + keys.len();
+
+        let mut data = SFO_MAGIC.to_vec();
+        data.extend(&0x0101u32.to_le_bytes());
+        data.extend(&(key_table as u32).to_le_bytes());
+        data.extend(&(data_table as u32).to_le_bytes());
+        data.extend(&(entries.len() as u32).to_le_bytes());
+        data.extend(index);
+        data.extend(keys);
+        data.extend(values);
+        data
+    }
+
+    fn fixture() -> Vec<u8> {
+        build(&[
+            ("APP_VER", FORMAT_STRING, b"01.02\0\0\0"),
+            ("ATTRIBUTE", FORMAT_INTEGER, &0x20u32.to_le_bytes()),
+            ("CATEGORY", FORMAT_STRING, b"HG\0\0"),
+            ("PADDING", FORMAT_BINARY, &[1, 0, 2, 0]),
+            ("TITLE", FORMAT_STRING, b"Example Game\0\0\0\0"),
+            ("TITLE_ID", FORMAT_STRING, b"BLUS30001\0\0\0"),
+        ])
+    }
+
+    #[test]
+    fn parses_strings_integers_and_binary_values() {
+        let sfo = ParamSfo::parse(&fixture()).unwrap();
+
+        assert_eq!(sfo.entries.len(), 6);
+        assert_eq!(sfo.title_id(), Some("BLUS30001"));
+        assert_eq!(sfo.app_version(), Some("01.02"));
+        assert_eq!(sfo.title(), Some("Example Game"));
+        assert_eq!(sfo.category(), Some("HG"));
+        assert_eq!(sfo.get_integer("ATTRIBUTE"), Some(0x20));
+        assert_eq!(
+            sfo.get("PADDING"),
+            Some(&SfoValue::Binary(vec![1, 0, 2, 0]))
+        );
+
+        // Values of another type are not returned
+        assert_eq!(sfo.get_str("ATTRIBUTE"), None);
+        assert_eq!(sfo.get_integer("TITLE"), None);
+        assert_eq!(sfo.get("MISSING"), None);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut data = fixture();
+        data[1] = b'X';
+        assert!(ParamSfo::parse(&data).is_err());
+
+        assert!(ParamSfo::parse(b"\0PSF").is_err());
+        assert!(ParamSfo::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data = fixture();
+
+        for length in (HEADER_SIZE..data.len()).step_by(7) {
+            assert!(
+                ParamSfo::parse(&data[..length]).is_err(),
+                "length {}",
+                length
+            );
+        }
+
+        assert!(ParamSfo::parse(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        let data = build(&[("TITLE_ID", 0x1234, b"BLUS30001\0\0\0")]);
+        assert!(ParamSfo::parse(&data).is_err());
+
+        let data = build(&[("ATTRIBUTE", FORMAT_INTEGER, &[0x20, 0x00])]);
+        assert!(ParamSfo::parse(&data).is_err());
+
+        let mut data = fixture();
+        data[0x10..0x14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ParamSfo::parse(&data).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{ConsoleType, FirmwareInfo, MemoryRegion, CCAPI, MEMORY_CHUNK_SIZE};
+use anyhow::{bail, ensure, Context, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CCDUMP\0\0";
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Memory saved from a single region of a process
+#[derive(Debug, Clone)]
+pub struct SnapshotRegion {
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+impl SnapshotRegion {
+    /// Returns the memory region covered by the saved data
+    pub fn region(&self) -> MemoryRegion {
+        MemoryRegion::new(self.address, self.data.len() as u64)
+    }
+}
+
+/// Saved memory regions of a process, along with information about where they came from
+///
+/// Snapshots are stored in a self-describing big-endian file format:
+///
+/// * Magic `CCDUMP\0\0` followed by the format version
+/// * Firmware version, CCAPI version and console type of the source console
+/// * Source pid and length-prefixed process name
+/// * Region table with the address and size of each region
+/// * Raw data of every region, in table order
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub firmware: FirmwareInfo,
+    pub pid: u32,
+    pub process_name: String,
+    pub regions: Vec<SnapshotRegion>,
+}
+
+impl Snapshot {
+    /// Reads the given regions of a process into a new snapshot
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to read from
+    /// * `pid` - The process identifier to read from
+    /// * `regions` - The memory regions to save
+    pub fn capture(ccapi: &CCAPI, pid: u32, regions: &[MemoryRegion]) -> Result<Self> {
+        Snapshot::capture_with_progress(ccapi, pid, regions, |_, _| {})
+    }
+
+    /// Same as [capture](crate::snapshot::Snapshot::capture), calling `progress`
+    /// with the amount of bytes read so far and the total after each chunk
+    pub fn capture_with_progress<F>(
+        ccapi: &CCAPI,
+        pid: u32,
+        regions: &[MemoryRegion],
+        mut progress: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(u64, u64),
+    {
+        let firmware = ccapi.get_firmware_info()?;
+        let process_name = ccapi.get_process_name(&pid)?;
+
+        let total: u64 = regions.iter().map(|region| region.size).sum();
+        let mut done = 0;
+        let mut saved_regions = Vec::with_capacity(regions.len());
+
+        for region in regions {
+            let mut data = Vec::with_capacity(region.size as usize);
+
+            for (address, size) in chunks(region) {
+                let chunk = ccapi.read_process_memory(&pid, &address, &size)?;
+                data.extend_from_slice(&chunk);
+
+                done += size as u64;
+                progress(done, total);
+            }
+
+            saved_regions.push(SnapshotRegion {
+                address: region.address,
+                data,
+            });
+        }
+
+        Ok(Snapshot {
+            firmware,
+            pid,
+            process_name,
+            regions: saved_regions,
+        })
+    }
+
+    /// Writes every saved region back into a process
+    ///
+    /// ### Arguments
+    ///
+    /// * `ccapi` - The console to write to
+    /// * `pid` - The process identifier to write to, which may differ from the source pid
+    pub fn restore(&self, ccapi: &CCAPI, pid: u32) -> Result<()> {
+        self.restore_with_progress(ccapi, pid, |_, _| {})
+    }
+
+    /// Same as [restore](crate::snapshot::Snapshot::restore), calling `progress`
+    /// with the amount of bytes written so far and the total after each chunk
+    pub fn restore_with_progress<F>(&self, ccapi: &CCAPI, pid: u32, mut progress: F) -> Result<()>
+    where
+        F: FnMut(u64, u64),
+    {
+        let total: u64 = self
+            .regions
+            .iter()
+            .map(|region| region.data.len() as u64)
+            .sum();
+        let mut done = 0;
+
+        for saved in &self.regions {
+            for (address, size) in chunks(&saved.region()) {
+                let start = (address - saved.address) as usize;
+                let bytes = &saved.data[start..start + size as usize];
+
+                ccapi.write_process_memory(&pid, &address, bytes)?;
+
+                done += size as u64;
+                progress(done, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the table of saved memory regions
+    pub fn region_table(&self) -> Vec<MemoryRegion> {
+        self.regions.iter().map(SnapshotRegion::region).collect()
+    }
+
+    /// Returns the saved bytes at the given address, if they are part of the snapshot
+    pub fn read(&self, address: u64, size: u64) -> Option<&[u8]> {
+        self.regions.iter().find_map(|saved| {
+            let region = saved.region();
+
+            match region.contains(address) && size <= region.end() - address {
+                true => {
+                    let start = (address - saved.address) as usize;
+                    Some(&saved.data[start..start + size as usize])
+                }
+                false => None,
+            }
+        })
+    }
+
+    /// Serializes the snapshot into the given writer
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_be_bytes())?;
+
+        writer.write_all(&self.firmware.firmware_version.to_be_bytes())?;
+        writer.write_all(&self.firmware.ccapi_version.to_be_bytes())?;
+        writer.write_all(&self.firmware.console_type.get_value().to_be_bytes())?;
+
+        writer.write_all(&self.pid.to_be_bytes())?;
+        writer.write_all(&(self.process_name.len() as u32).to_be_bytes())?;
+        writer.write_all(self.process_name.as_bytes())?;
+
+        writer.write_all(&(self.regions.len() as u32).to_be_bytes())?;
+
+        for saved in &self.regions {
+            writer.write_all(&saved.address.to_be_bytes())?;
+            writer.write_all(&(saved.data.len() as u64).to_be_bytes())?;
+        }
+
+        for saved in &self.regions {
+            writer.write_all(&saved.data)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Deserializes a snapshot from the given reader
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        ensure!(&magic == SNAPSHOT_MAGIC, "not a memory snapshot file");
+
+        let version = read_u32(&mut reader)?;
+        ensure!(
+            version == SNAPSHOT_VERSION,
+            "unsupported snapshot version '{version}'"
+        );
+
+        let firmware = FirmwareInfo {
+            firmware_version: read_u32(&mut reader)?,
+            ccapi_version: read_u32(&mut reader)?,
+            console_type: ConsoleType::from(read_u32(&mut reader)? as i32),
+        };
+
+        let pid = read_u32(&mut reader)?;
+
+        let name_length = read_u32(&mut reader)? as u64;
+        let raw_name = read_bytes(&mut reader, name_length).context("truncated process name")?;
+        let process_name = String::from_utf8(raw_name).context("invalid process name")?;
+
+        let region_count = read_u32(&mut reader)?;
+        let mut table = Vec::new();
+
+        for _ in 0..region_count {
+            let address = read_u64(&mut reader)?;
+            let size = read_u64(&mut reader)?;
+            ensure!(
+                address.checked_add(size).is_some(),
+                "region at '{address:#x}' with size '{size:#x}' overflows the address space"
+            );
+
+            table.push(MemoryRegion::new(address, size));
+        }
+
+        let mut regions = Vec::with_capacity(table.len());
+
+        for region in table {
+            let data = read_bytes(&mut reader, region.size)
+                .with_context(|| format!("truncated data for region at '{:#x}'", region.address))?;
+
+            regions.push(SnapshotRegion {
+                address: region.address,
+                data,
+            });
+        }
+
+        Ok(Snapshot {
+            firmware,
+            pid,
+            process_name,
+            regions,
+        })
+    }
+
+    /// Saves the snapshot to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    /// Loads a snapshot from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        Snapshot::read_from(BufReader::new(file))
+    }
+}
+
+/// Dumps the given regions of a process into a snapshot file
+///
+/// ### Arguments
+///
+/// * `ccapi` - The console to read from
+/// * `pid` - The process identifier to read from
+/// * `regions` - The memory regions to save
+/// * `path` - The file to write the snapshot to
+pub fn dump<P: AsRef<Path>>(
+    ccapi: &CCAPI,
+    pid: u32,
+    regions: &[MemoryRegion],
+    path: P,
+) -> Result<Snapshot> {
+    let snapshot = Snapshot::capture(ccapi, pid, regions)?;
+    snapshot.save(path)?;
+
+    Ok(snapshot)
+}
+
+/// Writes all regions saved in a snapshot file back into a process
+///
+/// ### Arguments
+///
+/// * `ccapi` - The console to write to
+/// * `pid` - The process identifier to write to
+/// * `path` - The snapshot file to restore
+pub fn restore<P: AsRef<Path>>(ccapi: &CCAPI, pid: u32, path: P) -> Result<Snapshot> {
+    let snapshot = Snapshot::load(path)?;
+
+    if snapshot.regions.is_empty() {
+        bail!("snapshot does not contain any memory regions");
+    }
+
+    snapshot.restore(ccapi, pid)?;
+
+    Ok(snapshot)
+}
+
+/// Splits a region into `(address, size)` pairs no larger than a single memory chunk
+pub(crate) fn chunks(region: &MemoryRegion) -> impl Iterator<Item = (u64, u32)> {
+    let end = region.end();
+
+    (region.address..end)
+        .step_by(MEMORY_CHUNK_SIZE as usize)
+        .map(move |address| {
+            (
+                address,
+                (end - address).min(MEMORY_CHUNK_SIZE as u64) as u32,
+            )
+        })
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Reads exactly `size` bytes, growing the buffer only as data actually arrives
+/// so a corrupt size cannot trigger a huge allocation
+fn read_bytes<R: Read>(reader: &mut R, size: u64) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(size).read_to_end(&mut data)?;
+
+    ensure!(
+        data.len() as u64 == size,
+        "expected {size} bytes, found {}",
+        data.len()
+    );
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            firmware: FirmwareInfo {
+                firmware_version: 0x48900,
+                ccapi_version: 0x2060,
+                console_type: ConsoleType::CEX,
+            },
+            pid: 0x1010200,
+            process_name: "/dev_hdd0/game/BLUS30001/USRDIR/EBOOT.BIN".to_string(),
+            regions: vec![
+                SnapshotRegion {
+                    address: 0x10000,
+                    data: vec![0x7C, 0x08, 0x02, 0xA6],
+                },
+                SnapshotRegion {
+                    address: 0x30000000,
+                    data: (0..=255).collect(),
+                },
+            ],
+        }
+    }
+
+    fn serialize(snapshot: &Snapshot) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        snapshot.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips_through_write_and_read() {
+        let original = snapshot();
+        let read = Snapshot::read_from(&serialize(&original)[..]).unwrap();
+
+        assert_eq!(read.firmware.firmware_version, 0x48900);
+        assert_eq!(read.firmware.ccapi_version, 0x2060);
+        assert_eq!(read.firmware.console_type, ConsoleType::CEX);
+        assert_eq!(read.pid, original.pid);
+        assert_eq!(read.process_name, original.process_name);
+        assert_eq!(read.regions.len(), 2);
+
+        for (read, original) in read.regions.iter().zip(&original.regions) {
+            assert_eq!(read.address, original.address);
+            assert_eq!(read.data, original.data);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let bytes = serialize(&snapshot());
+
+        for length in [0, 7, 12, 30, bytes.len() - 1].iter() {
+            assert!(Snapshot::read_from(&bytes[..*length]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_version() {
+        let mut bytes = serialize(&snapshot());
+        bytes[0] = b'X';
+        assert!(Snapshot::read_from(&bytes[..]).is_err());
+
+        let mut bytes = serialize(&snapshot());
+        bytes[11] = 2;
+        assert!(Snapshot::read_from(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn rejects_regions_overflowing_the_address_space() {
+        let mut original = snapshot();
+        original.regions.truncate(1);
+        original.regions[0].address = u64::MAX - 1;
+
+        let error = Snapshot::read_from(&serialize(&original)[..]).unwrap_err();
+        assert!(error.to_string().contains("overflows"));
+    }
+}
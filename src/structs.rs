@@ -0,0 +1,230 @@
+//! Reading and writing game structures described in Rust
+//!
+//! ```
+//! use ccapi::structs::{ConsoleStruct, Pointer};
+//!
+//! #[derive(ConsoleStruct)]
+//! #[size(0x20)]
+//! struct Player {
+//!     #[offset(0x00)]
+//!     health: u32,
+//!     #[offset(0x04)]
+//!     position: [f32; 3],
+//!     #[offset(0x1C)]
+//!     inventory: Pointer<u32>,
+//! }
+//!
+//! let mut bytes = [0u8; 0x20];
+//! bytes[0..4].copy_from_slice(&100u32.to_be_bytes());
+//! bytes[0x1C..0x20].copy_from_slice(&0x1000_0000u32.to_be_bytes());
+//!
+//! let player = Player::decode(&bytes).unwrap();
+//! assert_eq!(player.health, 100);
+//! assert_eq!(player.inventory.address, 0x1000_0000);
+//! ```
+
+use crate::CCAPI;
+use anyhow::{bail, ensure, Result};
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+pub use anyhow::Error;
+pub use ccapi_derive::ConsoleStruct;
+
+/// A value with a fixed big-endian layout in console memory
+///
+/// Usually implemented with `#[derive(ConsoleStruct)]`.
+pub trait ConsoleStruct: Sized {
+    /// Size of the value in console memory
+    const SIZE: usize;
+
+    /// Decodes the value from exactly [SIZE](ConsoleStruct::SIZE) bytes
+    fn decode(bytes: &[u8]) -> Result<Self>;
+
+    /// Encodes the value into exactly [SIZE](ConsoleStruct::SIZE) bytes
+    fn encode(&self, bytes: &mut [u8]) -> Result<()>;
+
+    /// Returns the encoded value as a new buffer
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; Self::SIZE];
+        self.encode(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Used by the derive macro to compute struct sizes
+#[doc(hidden)]
+pub const fn max_size(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Used by the derive macro to validate buffer lengths
+#[doc(hidden)]
+pub fn check_size<T: ConsoleStruct>(len: usize) -> Result<()> {
+    ensure!(
+        len == T::SIZE,
+        "expected {} bytes for {}, got {len}",
+        T::SIZE,
+        std::any::type_name::<T>()
+    );
+
+    Ok(())
+}
+
+/// Used by the derive macro to validate field placement against `#[size(..)]`
+#[doc(hidden)]
+pub fn field_range<T: ConsoleStruct>(
+    field: &str,
+    start: usize,
+    size: usize,
+) -> Result<Range<usize>> {
+    let end = start.checked_add(size);
+
+    match end {
+        Some(end) if end <= T::SIZE => Ok(start..end),
+        _ => bail!(
+            "field '{field}' at offset {start:#x} does not fit in the {:#x} bytes of {}",
+            T::SIZE,
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+macro_rules! impl_console_struct_for_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl ConsoleStruct for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn decode(bytes: &[u8]) -> Result<Self> {
+                    check_size::<Self>(bytes.len())?;
+                    Ok(<$ty>::from_be_bytes(bytes.try_into()?))
+                }
+
+                fn encode(&self, bytes: &mut [u8]) -> Result<()> {
+                    check_size::<Self>(bytes.len())?;
+                    bytes.copy_from_slice(&self.to_be_bytes());
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_console_struct_for_numbers!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl ConsoleStruct for bool {
+    const SIZE: usize = 1;
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        check_size::<Self>(bytes.len())?;
+        Ok(bytes[0] != 0)
+    }
+
+    fn encode(&self, bytes: &mut [u8]) -> Result<()> {
+        check_size::<Self>(bytes.len())?;
+        bytes[0] = *self as u8;
+        Ok(())
+    }
+}
+
+impl<T: ConsoleStruct, const N: usize> ConsoleStruct for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        check_size::<Self>(bytes.len())?;
+
+        let items = bytes
+            .chunks(T::SIZE.max(1))
+            .take(N)
+            .map(T::decode)
+            .collect::<Result<Vec<T>>>()?;
+
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("array length was checked above"),
+        }
+    }
+
+    fn encode(&self, bytes: &mut [u8]) -> Result<()> {
+        check_size::<Self>(bytes.len())?;
+
+        for (item, chunk) in self.iter().zip(bytes.chunks_mut(T::SIZE.max(1))) {
+            item.encode(chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A 32-bit pointer to a value in console memory
+pub struct Pointer<T> {
+    pub address: u32,
+    target: PhantomData<T>,
+}
+
+impl<T> Pointer<T> {
+    pub fn new(address: u32) -> Self {
+        Pointer {
+            address,
+            target: PhantomData,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.address == 0
+    }
+}
+
+impl<T: ConsoleStruct> Pointer<T> {
+    /// Reads the value the pointer points to
+    pub fn read(&self, ccapi: &CCAPI, pid: &u32) -> Result<T> {
+        ensure!(!self.is_null(), "cannot read through a null pointer");
+        ccapi.read_struct(pid, &(self.address as u64))
+    }
+
+    /// Writes a value to where the pointer points to
+    pub fn write(&self, ccapi: &CCAPI, pid: &u32, value: &T) -> Result<()> {
+        ensure!(!self.is_null(), "cannot write through a null pointer");
+        ccapi.write_struct(pid, &(self.address as u64), value)
+    }
+}
+
+// Implemented by hand so pointers do not require `T` to implement these traits
+impl<T> Clone for Pointer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Pointer<T> {}
+
+impl<T> PartialEq for Pointer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<T> fmt::Debug for Pointer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pointer({:#010x})", self.address)
+    }
+}
+
+impl<T> ConsoleStruct for Pointer<T> {
+    const SIZE: usize = 4;
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(Pointer::new(u32::decode(bytes)?))
+    }
+
+    fn encode(&self, bytes: &mut [u8]) -> Result<()> {
+        self.address.encode(bytes)
+    }
+}
@@ -0,0 +1,364 @@
+use crate::worker::{StopSignal, Worker};
+use crate::{CCAPI, MEMORY_CHUNK_SIZE};
+use anyhow::{bail, Result};
+use std::convert::TryInto;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_GAP: u64 = 0x40;
+
+/// The type used to interpret a watched address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Bytes(u32),
+}
+
+impl WatchType {
+    /// Returns the amount of bytes occupied by a value of this type
+    pub fn size(&self) -> u32 {
+        match *self {
+            WatchType::U8 | WatchType::I8 => 1,
+            WatchType::U16 | WatchType::I16 => 2,
+            WatchType::U32 | WatchType::I32 | WatchType::F32 => 4,
+            WatchType::U64 | WatchType::I64 | WatchType::F64 => 8,
+            WatchType::Bytes(size) => size,
+        }
+    }
+
+    /// Decodes a big-endian value of this type
+    pub fn decode(&self, bytes: &[u8]) -> Result<WatchValue> {
+        if bytes.len() != self.size() as usize {
+            bail!(
+                "expected {} bytes for {:?}, got {}",
+                self.size(),
+                self,
+                bytes.len()
+            );
+        }
+
+        let value = match *self {
+            WatchType::U8 => WatchValue::U8(bytes[0]),
+            WatchType::I8 => WatchValue::I8(bytes[0] as i8),
+            WatchType::U16 => WatchValue::U16(u16::from_be_bytes(bytes.try_into()?)),
+            WatchType::I16 => WatchValue::I16(i16::from_be_bytes(bytes.try_into()?)),
+            WatchType::U32 => WatchValue::U32(u32::from_be_bytes(bytes.try_into()?)),
+            WatchType::I32 => WatchValue::I32(i32::from_be_bytes(bytes.try_into()?)),
+            WatchType::F32 => WatchValue::F32(f32::from_be_bytes(bytes.try_into()?)),
+            WatchType::U64 => WatchValue::U64(u64::from_be_bytes(bytes.try_into()?)),
+            WatchType::I64 => WatchValue::I64(i64::from_be_bytes(bytes.try_into()?)),
+            WatchType::F64 => WatchValue::F64(f64::from_be_bytes(bytes.try_into()?)),
+            WatchType::Bytes(_) => WatchValue::Bytes(bytes.to_vec()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// A value read from a watched address
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchValue {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bytes(Vec<u8>),
+}
+
+/// Emitted whenever the value at a watched address changes
+#[derive(Debug, Clone)]
+pub struct WatchEvent {
+    /// Identifier returned by [MemoryWatch::watch](crate::watch::MemoryWatch::watch)
+    pub id: usize,
+    pub address: u64,
+    pub old: WatchValue,
+    pub new: WatchValue,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+struct Watch {
+    id: usize,
+    address: u64,
+    kind: WatchType,
+}
+
+/// A single read covering one or more watched addresses
+#[derive(Debug)]
+struct ReadGroup {
+    address: u64,
+    size: u32,
+    watches: Vec<Watch>,
+}
+
+/// Polls a set of typed addresses in a process and reports value changes
+pub struct MemoryWatch {
+    pid: u32,
+    interval: Duration,
+    max_gap: u64,
+    watches: Vec<Watch>,
+}
+
+impl MemoryWatch {
+    /// Returns a new watch for the given process without any addresses
+    pub fn new(pid: u32) -> Self {
+        MemoryWatch {
+            pid,
+            interval: DEFAULT_POLL_INTERVAL,
+            max_gap: DEFAULT_MAX_GAP,
+            watches: Vec::new(),
+        }
+    }
+
+    /// Sets how long to wait between each poll
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the largest distance between two addresses that are still read together
+    pub fn max_gap(mut self, max_gap: u64) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Adds an address to watch and returns its identifier
+    pub fn watch(&mut self, address: u64, kind: WatchType) -> usize {
+        let id = self.watches.len();
+        self.watches.push(Watch { id, address, kind });
+        id
+    }
+
+    /// Starts polling on a background thread, calling `callback` for every change
+    pub fn spawn<F>(self, ccapi: &CCAPI, mut callback: F) -> WatchHandle
+    where
+        F: FnMut(WatchEvent) + Send + 'static,
+    {
+        self.spawn_with(ccapi, move |event| {
+            callback(event);
+            true
+        })
+    }
+
+    /// Starts polling on a background thread, sending every change over a channel
+    ///
+    /// Polling stops once the receiver is dropped.
+    pub fn spawn_channel(self, ccapi: &CCAPI) -> (WatchHandle, Receiver<WatchEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let handle = self.spawn_with(ccapi, move |event| sender.send(event).is_ok());
+
+        (handle, receiver)
+    }
+
+    fn spawn_with<F>(self, ccapi: &CCAPI, emit: F) -> WatchHandle
+    where
+        F: FnMut(WatchEvent) -> bool + Send + 'static,
+    {
+        let ccapi = ccapi.clone();
+        let worker = Worker::spawn(move |signal| self.poll_loop(&ccapi, emit, signal));
+
+        WatchHandle { worker }
+    }
+
+    fn poll_loop<F>(&self, ccapi: &CCAPI, mut emit: F, signal: &StopSignal) -> Result<()>
+    where
+        F: FnMut(WatchEvent) -> bool,
+    {
+        let groups = group_reads(&self.watches, self.max_gap);
+        let mut previous: Vec<Option<Vec<u8>>> = vec![None; self.watches.len()];
+
+        loop {
+            for group in &groups {
+                let memory = ccapi.read_process_memory(&self.pid, &group.address, &group.size)?;
+                let timestamp = SystemTime::now();
+
+                for watch in &group.watches {
+                    let start = (watch.address - group.address) as usize;
+                    let current = &memory[start..start + watch.kind.size() as usize];
+
+                    let changed = match &previous[watch.id] {
+                        Some(old) if old.as_slice() != current => Some(old),
+                        _ => None,
+                    };
+
+                    if let Some(old) = changed {
+                        let event = WatchEvent {
+                            id: watch.id,
+                            address: watch.address,
+                            old: watch.kind.decode(old)?,
+                            new: watch.kind.decode(current)?,
+                            timestamp,
+                        };
+
+                        if !emit(event) {
+                            return Ok(());
+                        }
+                    }
+
+                    previous[watch.id] = Some(current.to_vec());
+                }
+            }
+
+            if !signal.sleep(self.interval) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Handle to a running [MemoryWatch](crate::watch::MemoryWatch)
+pub struct WatchHandle {
+    worker: Worker,
+}
+
+impl WatchHandle {
+    /// Returns whether the watch is still polling
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops polling, returning the error that ended the watch early (if any)
+    pub fn stop(mut self) -> Result<()> {
+        self.worker.stop()
+    }
+}
+
+/// Groups nearby watches into reads no larger than a single memory chunk
+fn group_reads(watches: &[Watch], max_gap: u64) -> Vec<ReadGroup> {
+    let mut sorted = watches.to_vec();
+    sorted.sort_by_key(|watch| watch.address);
+
+    let mut groups: Vec<ReadGroup> = Vec::new();
+
+    for watch in sorted {
+        let watch_end = watch.address + watch.kind.size() as u64;
+
+        if let Some(group) = groups.last_mut() {
+            let group_end = group.address + group.size as u64;
+            let merged_end = group_end.max(watch_end);
+
+            if watch.address <= group_end + max_gap
+                && merged_end - group.address <= MEMORY_CHUNK_SIZE as u64
+            {
+                group.size = (merged_end - group.address) as u32;
+                group.watches.push(watch);
+                continue;
+            }
+        }
+
+        groups.push(ReadGroup {
+            address: watch.address,
+            size: watch.kind.size(),
+            watches: vec![watch],
+        });
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(id: usize, address: u64, kind: WatchType) -> Watch {
+        Watch { id, address, kind }
+    }
+
+    fn spans(groups: &[ReadGroup]) -> Vec<(u64, u32, Vec<usize>)> {
+        groups
+            .iter()
+            .map(|group| {
+                let ids = group.watches.iter().map(|watch| watch.id).collect();
+                (group.address, group.size, ids)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_adjacent_watches() {
+        let watches = [
+            watch(0, 0x10004, WatchType::U32),
+            watch(1, 0x10000, WatchType::U32),
+            watch(2, 0x10008, WatchType::U8),
+        ];
+
+        assert_eq!(
+            spans(&group_reads(&watches, 0)),
+            vec![(0x10000, 9, vec![1, 0, 2])]
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_watches() {
+        let watches = [
+            watch(0, 0x10000, WatchType::U64),
+            watch(1, 0x10002, WatchType::U16),
+            watch(2, 0x10004, WatchType::F64),
+        ];
+
+        assert_eq!(
+            spans(&group_reads(&watches, 0)),
+            vec![(0x10000, 12, vec![0, 1, 2])]
+        );
+    }
+
+    #[test]
+    fn splits_at_gaps_larger_than_allowed() {
+        let watches = [
+            watch(0, 0x10000, WatchType::U32),
+            watch(1, 0x10010, WatchType::U32),
+            watch(2, 0x10100, WatchType::U32),
+        ];
+
+        assert_eq!(
+            spans(&group_reads(&watches, 0x10)),
+            vec![(0x10000, 0x14, vec![0, 1]), (0x10100, 4, vec![2])]
+        );
+    }
+
+    #[test]
+    fn splits_groups_larger_than_a_chunk() {
+        let chunk = MEMORY_CHUNK_SIZE;
+        let watches = [
+            watch(0, 0x10000, WatchType::Bytes(chunk - 4)),
+            watch(1, 0x10000 + chunk as u64 - 4, WatchType::U32),
+            watch(2, 0x10000 + chunk as u64, WatchType::U8),
+        ];
+
+        assert_eq!(
+            spans(&group_reads(&watches, 0)),
+            vec![
+                (0x10000, chunk, vec![0, 1]),
+                (0x10000 + chunk as u64, 1, vec![2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_single_watch_groups_within_a_chunk() {
+        assert!(group_reads(&[], DEFAULT_MAX_GAP).is_empty());
+
+        let watches = [watch(0, 0x10000, WatchType::U16)];
+
+        assert_eq!(
+            spans(&group_reads(&watches, DEFAULT_MAX_GAP)),
+            vec![(0x10000, 2, vec![0])]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Stop flag shared between a background worker and its owner
+#[derive(Clone, Default)]
+pub(crate) struct StopSignal {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl StopSignal {
+    pub(crate) fn stop(&self) {
+        let (stopped, condvar) = &*self.inner;
+        *stopped.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Sleeps for the given duration, returning `false` early if a stop was requested
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let (stopped, condvar) = &*self.inner;
+        let guard = stopped.lock().unwrap();
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, duration, |stopped| !*stopped)
+            .unwrap();
+
+        !*guard
+    }
+}
+
+/// A background thread that can be stopped and joined by its owner
+pub(crate) struct Worker {
+    signal: StopSignal,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl Worker {
+    pub(crate) fn spawn<F>(task: F) -> Self
+    where
+        F: FnOnce(&StopSignal) -> Result<()> + Send + 'static,
+    {
+        let signal = StopSignal::default();
+        let thread_signal = signal.clone();
+
+        let handle = thread::spawn(move || {
+            let result = task(&thread_signal);
+
+            // Mark the worker as finished when the task returns on its own
+            thread_signal.stop();
+            result
+        });
+
+        Worker {
+            signal,
+            handle: Some(handle),
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        !self.signal.is_stopped()
+    }
+
+    /// Requests the worker to stop and waits for it, returning the task result
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.signal.stop();
+        self.join()
+    }
+
+    /// Waits for the task to return on its own, returning its result
+    pub(crate) fn join(&mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| anyhow!("Background worker thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
@@ -0,0 +1,98 @@
+use ccapi::structs::{ConsoleStruct, Pointer};
+
+#[derive(Debug, PartialEq, ConsoleStruct)]
+struct Vector {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[derive(Debug, PartialEq, ConsoleStruct)]
+#[size(0x40)]
+struct Player {
+    #[offset(0x00)]
+    health: u32,
+    #[offset(0x04)]
+    position: Vector,
+    ammo: [u32; 4],
+    #[offset(0x30)]
+    alive: bool,
+    waypoints: [Vector; 0],
+    #[offset(0x3C)]
+    target: Pointer<Player>,
+}
+
+#[derive(Debug, PartialEq, ConsoleStruct)]
+struct Squad {
+    leader: Player,
+    flags: [u16; 3],
+}
+
+#[derive(Debug, ConsoleStruct)]
+#[size(0x4)]
+struct TooSmall {
+    value: u64,
+}
+
+fn player() -> Player {
+    Player {
+        health: 100,
+        position: Vector {
+            x: 1.0,
+            y: -2.5,
+            z: 1024.0,
+        },
+        ammo: [30, 0, 7, 0xFFFF_FFFF],
+        alive: true,
+        waypoints: [],
+        target: Pointer::new(0x3000_0000),
+    }
+}
+
+#[test]
+fn computes_sizes_of_nested_and_array_fields() {
+    assert_eq!(Vector::SIZE, 12);
+    assert_eq!(<[u32; 4]>::SIZE, 16);
+    assert_eq!(Player::SIZE, 0x40);
+    assert_eq!(Squad::SIZE, 0x46);
+}
+
+#[test]
+fn encodes_nested_struct_and_array_fields_in_place() {
+    let bytes = player().to_bytes().unwrap();
+
+    assert_eq!(bytes.len(), 0x40);
+    assert_eq!(&bytes[0x00..0x04], &100u32.to_be_bytes());
+    assert_eq!(&bytes[0x04..0x08], &1.0f32.to_be_bytes());
+    assert_eq!(&bytes[0x08..0x0C], &(-2.5f32).to_be_bytes());
+    assert_eq!(&bytes[0x0C..0x10], &1024.0f32.to_be_bytes());
+    assert_eq!(&bytes[0x10..0x14], &30u32.to_be_bytes());
+    assert_eq!(&bytes[0x18..0x1C], &7u32.to_be_bytes());
+    assert_eq!(&bytes[0x1C..0x20], &[0xFF; 4]);
+    assert_eq!(bytes[0x30], 1);
+    assert_eq!(&bytes[0x3C..0x40], &0x3000_0000u32.to_be_bytes());
+}
+
+#[test]
+fn round_trips_nested_structs() {
+    let squad = Squad {
+        leader: player(),
+        flags: [1, 0x8000, 0xFFFF],
+    };
+
+    let bytes = squad.to_bytes().unwrap();
+    assert_eq!(&bytes[0x40..0x46], &[0x00, 0x01, 0x80, 0x00, 0xFF, 0xFF]);
+    assert_eq!(Squad::decode(&bytes).unwrap(), squad);
+}
+
+#[test]
+fn rejects_buffers_of_the_wrong_size() {
+    assert!(Player::decode(&[0u8; 0x3F]).is_err());
+    assert!(player().encode(&mut [0u8; 0x41]).is_err());
+}
+
+#[test]
+fn rejects_fields_outside_the_declared_size() {
+    assert!(TooSmall::decode(&[0u8; 4]).is_err());
+    assert!(TooSmall { value: 1 }.to_bytes().is_err());
+}